@@ -14,7 +14,12 @@ use super::{super::Format, run::run};
 ///
 /// This function returns a [`CmdExit`] indicating the success or failure
 /// of the execution.
-pub fn exec(config: &Config, inject_folder: &Path, db_file: Option<PathBuf>) -> CmdExit {
+pub fn exec(
+    config: &Config,
+    inject_folder: &Path,
+    db_file: Option<PathBuf>,
+    deny_deprecated: bool,
+) -> CmdExit {
     let span = tracing::span!(tracing::Level::INFO, "checks");
     let _guard = span.enter();
     let injector = match run(config, inject_folder, db_file) {
@@ -25,9 +30,17 @@ pub fn exec(config: &Config, inject_folder: &Path, db_file: Option<PathBuf>) ->
     };
 
     let stats = injector.results.stats();
-    Format::Console.reporter().check(inject_folder, &stats);
+    let reporter = Format::Console.reporter();
+    reporter.check(inject_folder, &stats);
+    reporter.duplicates(inject_folder, &injector.duplicates);
 
-    if !stats.errors.is_empty() || stats.injects > 0 || stats.not_found_count > 0 {
+    if !stats.errors.is_empty()
+        || stats.injects > 0
+        || stats.not_found_count > 0
+        || stats.kind_mismatch_count > 0
+        || !injector.duplicates.is_empty()
+        || (deny_deprecated && stats.deprecated_count > 0)
+    {
         CmdExit::error()
     } else {
         CmdExit::ok()
@@ -28,6 +28,8 @@ use snipdoc::{
     walk,
 };
 
+use crate::Format;
+
 /// Executes `snipdoc create-db` command
 ///
 /// # Returns
@@ -55,7 +57,10 @@ pub fn exec(config: &Config, collect_folder: &Path, empty: bool) -> CmdExit {
             }
         };
 
-        let collector = Collector::walk(&walk);
+        let collector = Collector::walk(&walk, config);
+        Format::Console
+            .reporter()
+            .collect_errors(collect_folder, &collector.errors);
 
         let all_snippets: Vec<&snipdoc::parser::collector::CollectSnippet> =
             collector.snippets.values().flatten().collect();
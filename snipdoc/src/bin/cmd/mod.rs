@@ -1,4 +1,5 @@
 pub mod check;
 pub mod create_db;
+pub mod rename;
 pub mod run;
 pub mod show;
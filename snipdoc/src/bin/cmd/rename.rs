@@ -0,0 +1,201 @@
+//! This module provides cli command to rename a snippet id across the
+//! repository.
+//!
+//! The id is renamed in the snippet tags, in the placeholders and in the keys
+//! of the yaml DB. The files are otherwise kept as written.
+//!
+//! The ids are the qualified ids of the DB, e.g. `crate_a::installation`. A
+//! bare old id is enough when a single namespace defines it, and a bare new
+//! id is placed in the namespace of the renamed snippet.
+
+use std::path::{Path, PathBuf};
+
+use snipdoc::{
+    cli::CmdExit,
+    config::Config,
+    db::{self, DBData},
+    parser::{
+        renamer::{is_valid_id, Renamer},
+        syntax::NAMESPACE_SEPARATOR,
+    },
+    walk,
+};
+
+use super::{
+    super::Format,
+    run::{load_db, write_content},
+};
+
+/// Executes `snipdoc rename` command
+///
+/// # Returns
+///
+/// This function returns a [`CmdExit`] indicating the success or failure
+/// of the execution.
+pub fn exec(
+    config: &Config,
+    folder: &Path,
+    old: &str,
+    new: &str,
+    db_file: Option<PathBuf>,
+    dry_run: bool,
+) -> CmdExit {
+    let span = tracing::span!(tracing::Level::INFO, "rename", old, new);
+    let _guard = span.enter();
+
+    if !is_valid_id(new) {
+        return CmdExit::error_with_message(&format!("invalid snippet id `{new}`"));
+    }
+
+    // A file which snippets could not be collected from may define the new
+    // id, so the rename is refused rather than checked against partial data.
+    let db_data = match load_db(config, folder, db_file.clone()) {
+        Ok((db_data, errors)) if errors.is_empty() => db_data,
+        Ok((_, errors)) => {
+            Format::Console.reporter().collect_errors(folder, &errors);
+            return CmdExit::error_with_message("could not collect the snippets of all files");
+        }
+        Err(err) => {
+            return CmdExit::error_with_message(&format!("could not load the snippets: {err}"));
+        }
+    };
+    let (old, new) = match qualified_ids(&db_data, old, new) {
+        Ok(ids) => ids,
+        Err(message) => return CmdExit::error_with_message(&message),
+    };
+    if db_data.snippets.contains_key(&new) || db_data.aliases.contains_key(&new) {
+        return CmdExit::error_with_message(&format!("snippet `{new}` already exists"));
+    }
+
+    let walk = match walk::Walk::from_config(folder, &config.walk) {
+        Ok(walk) => walk,
+        Err(err) => {
+            return CmdExit::error_with_message(&format!("could not init walk instance: {err}"));
+        }
+    };
+    let renamed = Renamer::walk(&walk, config, &db_data, &old, &new);
+    if !renamed.errors.is_empty() {
+        Format::Console
+            .reporter()
+            .collect_errors(folder, &renamed.errors);
+        return CmdExit::error_with_message("could not rename the snippet in all files");
+    }
+
+    let yaml_db = db_file.map_or_else(
+        || db::Yaml::try_from_default_file(folder),
+        |db_file| Some(db::Yaml::new(db_file.as_path())),
+    );
+    let yaml_renamed = match yaml_db.as_ref().map(|yaml_db| yaml_db.rename(&old, &new)) {
+        Some(Ok(Some(content))) => yaml_db.map(|yaml_db| (yaml_db.path, content)),
+        Some(Err(err)) => {
+            return CmdExit::error_with_message(&format!("could not rename in the db file: {err}"));
+        }
+        _ => None,
+    };
+
+    if renamed.files.is_empty() && yaml_renamed.is_none() {
+        return CmdExit::error_with_message(&format!("snippet `{old}` not found"));
+    }
+
+    let root_folder = dunce::canonicalize(folder).unwrap_or_else(|_| folder.to_path_buf());
+    let path_view = |path: &Path| {
+        let path = dunce::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        path.strip_prefix(&root_folder)
+            .map_or_else(|_| path.clone(), Path::to_path_buf)
+    };
+
+    let files = renamed
+        .files
+        .iter()
+        .map(|(path, renamed)| {
+            (
+                path.clone(),
+                renamed.original.clone(),
+                renamed.content.clone(),
+            )
+        })
+        .chain(yaml_renamed.into_iter().map(|(path, content)| {
+            let original = std::fs::read_to_string(&path).unwrap_or_default();
+            (path, original, content)
+        }));
+
+    for (path, original, content) in files {
+        if dry_run {
+            print_diff(&path_view(&path), &original, &content);
+        } else if let Err(err) = write_content(&path, &content) {
+            return CmdExit::error_with_message(&format!(
+                "could not write {}: {err}",
+                path.display()
+            ));
+        } else {
+            println!(" - {}", path_view(&path).display());
+        }
+    }
+
+    if dry_run {
+        CmdExit::ok()
+    } else {
+        CmdExit::ok_with_message(&format!("Renamed snippet `{old}` to `{new}`"))
+    }
+}
+
+/// Resolves the old and new ids to qualified DB ids. A bare old id matches
+/// the snippet defined with this id in a single namespace, a bare new id is
+/// qualified with the namespace of the old one.
+fn qualified_ids(db_data: &DBData, old: &str, new: &str) -> Result<(String, String), String> {
+    let old = if db_data.snippets.contains_key(old) {
+        old.to_string()
+    } else {
+        let matches = db_data
+            .snippets
+            .keys()
+            .filter(|key| {
+                key.rsplit_once(NAMESPACE_SEPARATOR)
+                    .is_some_and(|(_, id)| id == old)
+            })
+            .collect::<Vec<_>>();
+        match matches.as_slice() {
+            [] => return Err(format!("snippet `{old}` not found")),
+            [key] => (*key).clone(),
+            keys => {
+                let keys = keys
+                    .iter()
+                    .map(|key| format!("`{key}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(format!(
+                    "snippet `{old}` is defined in several namespaces, use one of {keys}"
+                ));
+            }
+        }
+    };
+
+    let namespace = old
+        .rsplit_once(NAMESPACE_SEPARATOR)
+        .map(|(namespace, _)| namespace);
+    let new = match (namespace, new.rsplit_once(NAMESPACE_SEPARATOR)) {
+        (_, Some((new_namespace, _))) if Some(new_namespace) != namespace => {
+            return Err(format!(
+                "snippet `{old}` can't be moved to the namespace `{new_namespace}`"
+            ));
+        }
+        (Some(namespace), None) => format!("{namespace}{NAMESPACE_SEPARATOR}{new}"),
+        _ => new.to_string(),
+    };
+
+    Ok((old, new))
+}
+
+/// Prints the lines changed by the rename, the rename never adds or removes
+/// lines.
+fn print_diff(path: &Path, original: &str, content: &str) {
+    println!("--- {}", path.display());
+    println!("+++ {}", path.display());
+    for (number, (before, after)) in (1..).zip(original.lines().zip(content.lines())) {
+        if before != after {
+            println!("@@ -{number} +{number} @@");
+            println!("-{before}");
+            println!("+{after}");
+        }
+    }
+}
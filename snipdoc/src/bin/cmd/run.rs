@@ -6,6 +6,7 @@
 //! in the placeholders.
 
 use std::{
+    collections::BTreeMap,
     fs,
     io::{self, Write},
     path::{Path, PathBuf},
@@ -14,7 +15,8 @@ use std::{
 use snipdoc::{
     cli::CmdExit,
     config::Config,
-    db::{self, Db},
+    db::{self, DBData, Db},
+    errors::Diagnostic,
     parser::{
         collector::Collector,
         injector::{InjectedContent, Injector, InjectorResult},
@@ -64,6 +66,30 @@ pub fn run(
     inject_folder: &Path,
     db_file: Option<PathBuf>,
 ) -> io::Result<InjectorResult> {
+    let (db_data, collect_errors) = load_db(config, inject_folder, db_file)?;
+
+    let walk = match walk::Walk::from_config(inject_folder, &config.walk) {
+        Ok(walk) => walk,
+        Err(err) => {
+            return Err(err);
+        }
+    };
+
+    // Files which snippets could not be collected from are reported as
+    // errors, otherwise their placeholders are only seen as not found.
+    let mut injector = Injector::walk(&walk, &db_data, config);
+    injector.results.add_errors(&collect_errors);
+
+    Ok(injector)
+}
+
+/// Collects the snippets from the code and merges the snippets of the yaml
+/// db, returning the files which snippets could not be collected from.
+pub fn load_db(
+    config: &Config,
+    inject_folder: &Path,
+    db_file: Option<PathBuf>,
+) -> io::Result<(DBData, BTreeMap<PathBuf, Diagnostic>)> {
     // first search a snippets from the code
     let walk = match walk::Walk::from_config(inject_folder, &config.walk) {
         Ok(walk) => walk,
@@ -72,9 +98,8 @@ pub fn run(
         }
     };
 
-    let mut db_data = db::Code::new(Collector::walk(&walk).snippets)
-        .load()
-        .unwrap();
+    let collected = Collector::walk(&walk, config);
+    let mut db_data = db::Code::new(collected.snippets).load().unwrap();
 
     // Then if db_file is given, load the snippets from the yaml,
     // If the db_file not given, search if the default snippet file name is
@@ -89,9 +114,7 @@ pub fn run(
     // If yaml db is configured, load all the snippets from the yaml and append to
     // the existing  snippets
     if let Some(yaml_db) = &maybe_yaml_file {
-        let snippets_from_yaml = yaml_db.load().unwrap();
-        db_data.snippets.extend(snippets_from_yaml.snippets);
-        db_data.templates = snippets_from_yaml.templates;
+        db_data.merge(yaml_db.load().unwrap(), &config.sources);
         tracing::debug!(
             snippet_count = db_data.snippets.len(),
             template_count = db_data.templates.len(),
@@ -99,17 +122,10 @@ pub fn run(
         );
     }
 
-    let walk = match walk::Walk::from_config(inject_folder, &config.walk) {
-        Ok(walk) => walk,
-        Err(err) => {
-            return Err(err);
-        }
-    };
-
-    Ok(Injector::walk(&walk, &db_data, &config.inject))
+    Ok((db_data, collected.errors))
 }
 
-fn write_content(path: &Path, content: &str) -> std::io::Result<()> {
+pub fn write_content(path: &Path, content: &str) -> std::io::Result<()> {
     let mut file = fs::File::create(path)?;
     file.write_all(content.as_bytes())?;
     Ok(())
@@ -1,6 +1,9 @@
 //! This module provides cli command to preview all the snippets
 
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use snipdoc::{
     cli::CmdExit,
@@ -36,13 +39,14 @@ pub fn exec(
     };
 
     let mut snippets_data = DBData::default();
+    let mut collect_errors = BTreeMap::new();
 
     if snippet_kind == &SnippetKind::Code || snippet_kind == &SnippetKind::Any {
-        let code_snippets = db::Code::new(Collector::walk(&walk).snippets)
-            .load()
-            .unwrap();
+        let collected = Collector::walk(&walk, config);
+        let code_snippets = db::Code::new(collected.snippets).load().unwrap();
 
-        snippets_data.snippets.extend(code_snippets.snippets);
+        snippets_data.merge(code_snippets, &config.sources);
+        collect_errors = collected.errors;
     }
 
     if snippet_kind == &SnippetKind::Yaml || snippet_kind == &SnippetKind::Any {
@@ -54,14 +58,13 @@ pub fn exec(
         };
 
         if let Some(yaml_db) = &maybe_yaml_file {
-            let snippets_from_yaml = yaml_db.load().unwrap();
-            snippets_data.snippets.extend(snippets_from_yaml.snippets);
+            snippets_data.merge(yaml_db.load().unwrap(), &config.sources);
         }
     }
 
-    format
-        .reporter()
-        .snippets(inject_folder, &snippets_data.snippets);
+    let reporter = format.reporter();
+    reporter.snippets(inject_folder, &snippets_data.snippets);
+    reporter.collect_errors(inject_folder, &collect_errors);
 
     CmdExit::ok()
 }
@@ -56,6 +56,10 @@ enum Commands {
     Check {
         #[arg(long, default_value = None)]
         db_file: Option<PathBuf>,
+
+        /// Fail when a placeholder references a deprecated snippet or alias
+        #[clap(long, action=ArgAction::SetTrue)]
+        deny_deprecated: bool,
     },
     /// Inject snippet into placeholders
     Run {
@@ -70,6 +74,25 @@ enum Commands {
         #[arg(long, value_enum, default_value_t = Format::default())]
         format: Format,
     },
+    /// Rename a snippet id in the snippets, placeholders and DB file
+    Rename {
+        /// The current snippet id
+        old: String,
+
+        /// The new snippet id
+        new: String,
+
+        /// Source code directory for collecting documentation
+        #[arg(long, default_value = ".")]
+        path: PathBuf,
+
+        #[arg(long, default_value = None)]
+        db_file: Option<PathBuf>,
+
+        /// Show the changes as a diff without writing them
+        #[clap(long, action=ArgAction::SetTrue)]
+        dry_run: bool,
+    },
     /// Show snippets
     Show {
         #[arg(long,value_enum, default_value_t = SnippetKind::default())]
@@ -106,12 +129,22 @@ fn main() {
 
     match app.command {
         Commands::CreateDb { empty } => cmd::create_db::exec(&config, app.path.as_path(), empty),
-        Commands::Check { db_file } => cmd::check::exec(&config, app.path.as_path(), db_file),
+        Commands::Check {
+            db_file,
+            deny_deprecated,
+        } => cmd::check::exec(&config, app.path.as_path(), db_file, deny_deprecated),
         Commands::Run {
             db_file,
             dry_run,
             format,
         } => cmd::run::exec(&config, app.path.as_path(), db_file, dry_run, &format),
+        Commands::Rename {
+            old,
+            new,
+            path: _,
+            db_file,
+            dry_run,
+        } => cmd::rename::exec(&config, app.path.as_path(), &old, &new, db_file, dry_run),
         Commands::Show {
             from,
             db_file,
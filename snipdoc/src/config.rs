@@ -1,12 +1,15 @@
 //! This module provides configuration management for the `snipdoc`. It
 //! includes functionality to load and manage configurations from a default YAML
 //! file.
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::errors::ConfigResult;
+use crate::{
+    errors::ConfigResult,
+    parser::{escape::Escape, CompareMode, NestedMode, SnippetKind},
+};
 
 pub const DEFAULT_CONFIG_NAME: &str = "snipdoc-config.yml";
 
@@ -15,11 +18,79 @@ pub struct Config {
     pub walk: WalkConfig,
     #[serde(default)]
     pub inject: InjectConfig,
+    /// Comment tokens that may wrap snip tags, keyed by a file glob (`*.sql`)
+    /// or a file extension (`lua`). Files without a matching entry use the
+    /// built-in tokens.
+    #[serde(default)]
+    pub comments: BTreeMap<String, Vec<CommentToken>>,
+    /// File globs (`*.txt`) or extensions (`py`) in which snip tags alone on
+    /// a line are recognized without a comment token.
+    #[serde(default)]
+    pub bare_tags: Vec<String>,
+    /// File globs (`docs/*.md`) or extensions (`md`) of markdown files in
+    /// which tags inside fenced code blocks are examples and are not parsed.
+    /// Tags inside inline code are always examples.
+    #[serde(default)]
+    pub fenced_examples: Vec<String>,
+    /// The element name of the tags, `snip` when not set.
+    #[serde(default)]
+    pub keyword: Option<String>,
+    /// Snippet sources ordered by precedence, e.g. `[code, yaml]`. When an
+    /// id is defined in several sources, the first listed source wins. When
+    /// not set, yaml snippets replace code snippets.
+    #[serde(default)]
+    pub sources: Vec<SnippetKind>,
+    /// Namespaces keyed by directory (`crates/a`). Snippets defined under the
+    /// directory get ids like `crate_a::installation`, and placeholders under
+    /// it look in the namespace first.
+    #[serde(default)]
+    pub namespaces: BTreeMap<String, String>,
+}
+
+/// A comment syntax that can wrap a snip tag, e.g. `--` or `<!--` / `-->`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommentToken {
+    /// The token opening the comment.
+    pub open: String,
+    /// The token closing the comment. Empty for line comments.
+    #[serde(default)]
+    pub close: Option<String>,
+}
+
+impl CommentToken {
+    #[must_use]
+    pub fn line(open: &str) -> Self {
+        Self {
+            open: open.to_string(),
+            close: None,
+        }
+    }
+
+    #[must_use]
+    pub fn block(open: &str, close: &str) -> Self {
+        Self {
+            open: open.to_string(),
+            close: Some(close.to_string()),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 #[allow(clippy::module_name_repetitions)]
-pub struct InjectConfig {}
+pub struct InjectConfig {
+    /// How nested snippets are injected when the placeholder has no `nested`
+    /// attribute.
+    #[serde(default)]
+    pub nested: NestedMode,
+    /// How `run` and `check` decide that an injected block is up to date.
+    #[serde(default)]
+    pub compare: CompareMode,
+    /// How the snippets are escaped when the placeholder has no `escape`
+    /// attribute, keyed by a file glob (`*.html`) or a file extension
+    /// (`json`).
+    #[serde(default)]
+    pub escape: BTreeMap<String, Escape>,
+}
 
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 #[allow(clippy::module_name_repetitions)]
@@ -3,7 +3,7 @@
 //! `snipdoc`.
 use std::{collections::BTreeMap, path::PathBuf};
 
-use super::{DBData, Db, Result, Snippet};
+use super::{DBData, Db, Definition, Result, Snippet};
 use crate::parser::{collector::CollectSnippet, SnippetKind};
 
 pub struct Code {
@@ -26,13 +26,23 @@ impl Db for Code {
                 // when loading snippets from the code, we should get only snippets and not
                 // placeholder that we should inject
                 if snippet.inject_from.is_none() {
-                    data.snippets.insert(
-                        snippet.id.clone(),
+                    data.insert(
                         Snippet {
                             id: snippet.id.clone(),
                             content: snippet.snippet.join(crate::LINE_ENDING),
                             kind: SnippetKind::Code,
                             path: path.clone(),
+                            nested: snippet.nested.clone(),
+                            hidden: snippet.hidden.clone(),
+                            lines: snippet.lines,
+                            aliases: snippet.aliases.clone(),
+                            deprecated: snippet.deprecated.clone(),
+                        },
+                        Definition {
+                            kind: SnippetKind::Code,
+                            path: path.clone(),
+                            line: Some(snippet.line),
+                            allow_override: snippet.allow_override,
                         },
                     );
                 }
@@ -77,6 +87,13 @@ mod tests {
             inject_from: None,
             tag_open: "<snip id=\"description\">".to_string(),
             tag_close: "<!-- </snip> -->\n".to_string(),
+            nested: vec![],
+            hidden: vec![],
+            lines: None,
+            line: 1,
+            allow_override: false,
+            aliases: vec![],
+            deprecated: None,
         };
 
         assert!(code.save(&[&save_snippets], &BTreeMap::new()).is_err());
@@ -1,9 +1,15 @@
 mod code;
 mod yaml;
 
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Component, Path, PathBuf},
+};
 
-use crate::parser::{collector::CollectSnippet, Snippet, SnippetTemplate};
+use crate::parser::{
+    collector::CollectSnippet, syntax::NAMESPACE_SEPARATOR, template::Engine, Snippet, SnippetKind,
+    SnippetTemplate,
+};
 pub use code::Code;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
@@ -16,6 +22,13 @@ lazy_static! {
         tag_open: String::new(),
         tag_close: String::new(),
         snippet: vec![String::new()],
+        nested: vec![],
+        hidden: vec![],
+        lines: None,
+        line: 1,
+        allow_override: false,
+        aliases: vec![],
+        deprecated: None,
     }];
     pub static ref EMPTY_TEMPLATE_SNIPPETS: BTreeMap<String, SnippetTemplate> = BTreeMap::from([(
         "TEMPLATE_ID".to_string(),
@@ -24,6 +37,7 @@ lazy_static! {
 {snippet}
 ```"
             .to_string(),
+            engine: Engine::Legacy,
         }
     )]);
 }
@@ -61,6 +75,268 @@ pub trait Db {
 pub struct DBData {
     pub snippets: BTreeMap<String, Snippet>,
     pub templates: BTreeMap<String, SnippetTemplate>,
+    /// Every place a snippet id is defined, in load order.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub definitions: BTreeMap<String, Vec<Definition>>,
+    /// Snippets replaced by a snippet with the same id, in load order. They
+    /// are still injected by placeholders asking for their source.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shadowed: BTreeMap<String, Vec<Snippet>>,
+    /// Former snippet ids, keyed to the id they resolve to.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// A snippet found for a placeholder.
+#[derive(Debug, Clone, Copy)]
+pub struct Resolved<'a> {
+    pub snippet: &'a Snippet,
+    /// The alias the snippet was found by, if not found by its id.
+    pub alias: Option<&'a str>,
+}
+
+/// Where a placeholder looks up the snippets it refers to.
+#[derive(Debug, Clone, Copy)]
+pub struct Scope<'a> {
+    /// The namespace searched before the global one.
+    pub namespace: Option<&'a str>,
+    /// The folder of the file containing the placeholder, `./dir#id`
+    /// references are relative to it.
+    pub root: &'a Path,
+}
+
+/// A place where a snippet is defined.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    pub kind: SnippetKind,
+    /// The file defining the snippet.
+    pub path: PathBuf,
+    /// The line of the snippet open tag, unknown for yaml snippets.
+    pub line: Option<usize>,
+    /// The definition intentionally shadows the other definitions.
+    pub allow_override: bool,
+}
+
+impl DBData {
+    /// Adds a snippet and records where it is defined. A snippet replaces an
+    /// existing one with the same id, unless only the existing one is marked
+    /// with `allow_override`.
+    pub fn insert(&mut self, snippet: Snippet, definition: Definition) {
+        self.add(snippet, vec![definition], &[]);
+    }
+
+    /// Merges the snippets of another source. `sources` orders the snippet
+    /// kinds by precedence, a snippet replaces an existing one with the same
+    /// id when its kind comes first or equal. Kinds missing from `sources`
+    /// come last. A snippet marked with `allow_override` always wins over
+    /// one which is not.
+    pub fn merge(&mut self, other: Self, sources: &[SnippetKind]) {
+        let Self {
+            snippets,
+            templates,
+            mut definitions,
+            shadowed,
+            aliases,
+        } = other;
+
+        self.templates.extend(templates);
+        for (alias, id) in aliases {
+            self.aliases.entry(alias).or_insert(id);
+        }
+        for (id, snippets) in shadowed {
+            self.shadowed.entry(id).or_default().extend(snippets);
+        }
+        for (id, snippet) in snippets {
+            let definitions = definitions.remove(&id).unwrap_or_default();
+            self.add(snippet, definitions, sources);
+        }
+    }
+
+    fn add(&mut self, snippet: Snippet, definitions: Vec<Definition>, sources: &[SnippetKind]) {
+        let overrides = |definitions: &[Definition]| {
+            definitions
+                .iter()
+                .any(|definition| definition.allow_override)
+        };
+        let rank = |kind: &SnippetKind| {
+            sources
+                .iter()
+                .position(|source| source == kind)
+                .unwrap_or(sources.len())
+        };
+
+        for alias in &snippet.aliases {
+            self.aliases
+                .entry(alias.clone())
+                .or_insert_with(|| snippet.id.clone());
+        }
+
+        let existing = self.definitions.entry(snippet.id.clone()).or_default();
+        let (new_override, existing_override) = (overrides(&definitions), overrides(existing));
+        existing.extend(definitions);
+
+        let replace = self.snippets.get(&snippet.id).is_none_or(|current| {
+            if new_override == existing_override {
+                rank(&snippet.kind) <= rank(&current.kind)
+            } else {
+                new_override
+            }
+        });
+
+        let shadowed = if replace {
+            self.snippets.insert(snippet.id.clone(), snippet)
+        } else {
+            Some(snippet)
+        };
+        if let Some(shadowed) = shadowed {
+            self.shadowed
+                .entry(shadowed.id.clone())
+                .or_default()
+                .push(shadowed);
+        }
+    }
+
+    /// Returns the snippet to inject from the given source. When the id is
+    /// defined in several matching sources, the snippet from `prefer` is
+    /// used if any, otherwise the snippet winning the precedence.
+    ///
+    /// A `./dir#id` reference matches the snippets defined under the
+    /// directory, relative to the placeholder file. Other ids, including ids
+    /// containing `#`, are looked up in the namespace of the scope
+    /// first, then globally. An id which is not defined is looked up in the
+    /// aliases.
+    #[must_use]
+    pub fn get(
+        &self,
+        id: &str,
+        scope: &Scope<'_>,
+        inject_from: &SnippetKind,
+        prefer: Option<&SnippetKind>,
+    ) -> Option<Resolved<'_>> {
+        let reference = id
+            .strip_prefix("./")
+            .and_then(|reference| reference.split_once('#'));
+        let candidates = if let Some((dir, local_id)) = reference {
+            let dir = without_cur_dir(&scope.root.join(dir));
+            self.snippets
+                .keys()
+                .chain(self.aliases.keys())
+                .filter(|key| key.rsplit(NAMESPACE_SEPARATOR).next() == Some(local_id))
+                .flat_map(|key| self.candidates(key, inject_from))
+                .filter(|resolved| without_cur_dir(&resolved.snippet.path).starts_with(&dir))
+                .collect()
+        } else {
+            scope
+                .namespace
+                .map(|namespace| {
+                    self.candidates(
+                        &format!("{namespace}{NAMESPACE_SEPARATOR}{id}"),
+                        inject_from,
+                    )
+                })
+                .filter(|candidates| !candidates.is_empty())
+                .unwrap_or_else(|| self.candidates(id, inject_from))
+        };
+
+        prefer
+            .and_then(|prefer| {
+                candidates
+                    .iter()
+                    .find(|resolved| prefer.matches(&resolved.snippet.kind))
+            })
+            .or_else(|| candidates.first())
+            .copied()
+    }
+
+    /// Returns the snippets with the given id or alias from the given source,
+    /// the snippet winning the precedence first.
+    fn candidates(&self, id: &str, inject_from: &SnippetKind) -> Vec<Resolved<'_>> {
+        let (id, alias) = match self.aliases.get_key_value(id) {
+            Some((alias, target)) if !self.snippets.contains_key(id) => {
+                (target.as_str(), Some(alias.as_str()))
+            }
+            _ => (id, None),
+        };
+
+        self.snippets
+            .get(id)
+            .into_iter()
+            .chain(
+                self.shadowed
+                    .get(id)
+                    .into_iter()
+                    .flat_map(|s| s.iter().rev()),
+            )
+            .filter(|snippet| inject_from.matches(&snippet.kind))
+            .map(|snippet| Resolved { snippet, alias })
+            .collect()
+    }
+
+    /// Returns up to three existing ids close to the given id, closest first,
+    /// to help fixing a typo in a placeholder. An id is close when its edit
+    /// distance is at most a third of the id length, or when one id is a
+    /// prefix of the other. An alias named like a snippet id is suggested
+    /// once.
+    #[must_use]
+    pub fn suggestions(&self, id: &str) -> Vec<String> {
+        let max_distance = (id.chars().count() / 3).max(1);
+
+        let suggestions = self
+            .snippets
+            .keys()
+            .chain(self.aliases.keys())
+            .filter(|candidate| candidate.as_str() != id)
+            .filter_map(|candidate| {
+                let distance = edit_distance(id, candidate);
+                let is_prefix = id.len() >= 3
+                    && candidate.len() >= 3
+                    && (candidate.starts_with(id) || id.starts_with(candidate.as_str()));
+                (distance <= max_distance || is_prefix).then_some((distance, candidate))
+            })
+            .collect::<BTreeSet<_>>();
+
+        suggestions
+            .into_iter()
+            .take(3)
+            .map(|(_, candidate)| candidate.clone())
+            .collect()
+    }
+
+    /// Returns the snippet ids defined more than once. Definitions marked
+    /// with `allow_override` are expected to shadow others and are not
+    /// reported.
+    #[must_use]
+    pub fn duplicates(&self) -> BTreeMap<String, Vec<Definition>> {
+        self.definitions
+            .iter()
+            .filter(|(_, definitions)| {
+                definitions
+                    .iter()
+                    .filter(|definition| !definition.allow_override)
+                    .count()
+                    > 1
+            })
+            .map(|(id, definitions)| (id.clone(), definitions.clone()))
+            .collect()
+    }
+}
+
+/// The number of single character insertions, deletions and substitutions
+/// turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != *b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -76,3 +352,219 @@ pub enum Error {
 }
 
 pub type Result<'a, T> = std::result::Result<T, Error>;
+
+/// Drops the `.` components, so `./docs` and `docs` are the same folder.
+fn without_cur_dir(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| component != &Component::CurDir)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(id: &str, content: &str, kind: SnippetKind) -> Snippet {
+        Snippet {
+            id: id.to_string(),
+            content: content.to_string(),
+            kind,
+            path: PathBuf::from("main.rs"),
+            nested: vec![],
+            hidden: vec![],
+            lines: None,
+            aliases: vec![],
+            deprecated: None,
+        }
+    }
+
+    fn definition(kind: SnippetKind, path: &str, allow_override: bool) -> Definition {
+        Definition {
+            kind,
+            path: PathBuf::from(path),
+            line: None,
+            allow_override,
+        }
+    }
+
+    #[test]
+    fn can_detect_duplicates() {
+        let mut code = DBData::default();
+        code.insert(
+            snippet("one", "from a.rs", SnippetKind::Code),
+            definition(SnippetKind::Code, "a.rs", false),
+        );
+        code.insert(
+            snippet("one", "from b.rs", SnippetKind::Code),
+            definition(SnippetKind::Code, "b.rs", false),
+        );
+        code.insert(
+            snippet("two", "override", SnippetKind::Code),
+            definition(SnippetKind::Code, "a.rs", true),
+        );
+
+        let mut yaml = DBData::default();
+        yaml.insert(
+            snippet("two", "from yaml", SnippetKind::Yaml),
+            definition(SnippetKind::Yaml, "snipdoc.yml", false),
+        );
+        code.merge(yaml, &[]);
+
+        assert_eq!(code.snippets["one"].content, "from b.rs");
+        assert_eq!(code.snippets["two"].content, "override");
+        assert_eq!(
+            code.duplicates(),
+            BTreeMap::from([(
+                "one".to_string(),
+                vec![
+                    definition(SnippetKind::Code, "a.rs", false),
+                    definition(SnippetKind::Code, "b.rs", false),
+                ]
+            )])
+        );
+    }
+
+    #[test]
+    fn can_merge_by_source_precedence() {
+        let load = |sources: &[SnippetKind]| {
+            let mut code = DBData::default();
+            code.insert(
+                snippet("one", "from code", SnippetKind::Code),
+                definition(SnippetKind::Code, "a.rs", false),
+            );
+            let mut yaml = DBData::default();
+            yaml.insert(
+                snippet("one", "from yaml", SnippetKind::Yaml),
+                definition(SnippetKind::Yaml, "snipdoc.yml", false),
+            );
+            code.merge(yaml, sources);
+            code
+        };
+        let content = |resolved: Option<Resolved<'_>>| resolved.map(|r| r.snippet.content.clone());
+        let scope = Scope {
+            namespace: None,
+            root: Path::new("."),
+        };
+
+        let data = load(&[]);
+        assert_eq!(data.snippets["one"].content, "from yaml");
+
+        let data = load(&[SnippetKind::Code, SnippetKind::Yaml]);
+        assert_eq!(data.snippets["one"].content, "from code");
+        assert_eq!(
+            content(data.get("one", &scope, &SnippetKind::Any, None)),
+            Some("from code".to_string())
+        );
+        assert_eq!(
+            content(data.get("one", &scope, &SnippetKind::Any, Some(&SnippetKind::Yaml))),
+            Some("from yaml".to_string())
+        );
+        assert_eq!(
+            content(data.get("one", &scope, &SnippetKind::Yaml, None)),
+            Some("from yaml".to_string())
+        );
+        assert_eq!(
+            content(data.get("two", &scope, &SnippetKind::Any, None)),
+            None
+        );
+    }
+
+    #[test]
+    fn can_resolve_aliases() {
+        let mut data = DBData::default();
+        data.insert(
+            Snippet {
+                aliases: vec!["old".to_string()],
+                ..snippet("new", "content", SnippetKind::Code)
+            },
+            definition(SnippetKind::Code, "a.rs", false),
+        );
+        let scope = Scope {
+            namespace: None,
+            root: Path::new("."),
+        };
+
+        let resolved = data.get("old", &scope, &SnippetKind::Any, None).unwrap();
+        assert_eq!(resolved.snippet.id, "new");
+        assert_eq!(resolved.alias, Some("old"));
+
+        let resolved = data.get("new", &scope, &SnippetKind::Any, None).unwrap();
+        assert_eq!(resolved.alias, None);
+    }
+
+    #[test]
+    fn can_resolve_path_references() {
+        let mut data = DBData::default();
+        for (id, path) in [
+            ("crate_a::installation", "crates/a/lib.rs"),
+            ("crate_b::installation", "crates/b/lib.rs"),
+            ("c#-usage", "main.rs"),
+        ] {
+            data.insert(
+                Snippet {
+                    path: PathBuf::from(path),
+                    ..snippet(id, path, SnippetKind::Code)
+                },
+                definition(SnippetKind::Code, path, false),
+            );
+        }
+        let content = |id: &str, root: &str| {
+            let scope = Scope {
+                namespace: None,
+                root: Path::new(root),
+            };
+            data.get(id, &scope, &SnippetKind::Any, None)
+                .map(|r| r.snippet.content.clone())
+        };
+
+        assert_eq!(
+            content("./crates/a#installation", "."),
+            Some("crates/a/lib.rs".to_string())
+        );
+        assert_eq!(
+            content("./b#installation", "crates"),
+            Some("crates/b/lib.rs".to_string())
+        );
+        assert_eq!(content("./a#installation", "."), None);
+        assert_eq!(content("c#-usage", "crates"), Some("main.rs".to_string()));
+    }
+
+    #[test]
+    fn can_suggest_close_ids() {
+        let mut data = DBData::default();
+        for id in ["installation", "install-guide", "usage", "cli"] {
+            data.insert(
+                snippet(id, "content", SnippetKind::Code),
+                definition(SnippetKind::Code, "a.rs", false),
+            );
+        }
+
+        assert_eq!(edit_distance("instalation", "installation"), 1);
+        assert_eq!(data.suggestions("instalation"), vec!["installation"]);
+        assert_eq!(
+            data.suggestions("install"),
+            vec!["installation", "install-guide"]
+        );
+        assert_eq!(data.suggestions("clii"), vec!["cli"]);
+        assert!(data.suggestions("unknown").is_empty());
+    }
+
+    #[test]
+    fn can_suggest_alias_named_like_an_id_once() {
+        let mut data = DBData::default();
+        data.insert(
+            snippet("installation", "content", SnippetKind::Code),
+            definition(SnippetKind::Code, "a.rs", false),
+        );
+        data.insert(
+            Snippet {
+                aliases: vec!["installation".to_string(), "setup-old".to_string()],
+                ..snippet("setup", "content", SnippetKind::Code)
+            },
+            definition(SnippetKind::Code, "b.rs", false),
+        );
+
+        assert_eq!(data.suggestions("instalation"), vec!["installation"]);
+        assert_eq!(data.suggestions("setup-ol"), vec!["setup-old", "setup"]);
+    }
+}
@@ -8,8 +8,10 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
-use super::{DBData, Db, Result, Snippet};
-use crate::parser::{collector::CollectSnippet, SnippetKind, SnippetTemplate};
+use super::{DBData, Db, Definition, Result, Snippet};
+use crate::parser::{
+    collector::CollectSnippet, HiddenLines, NestedLines, SnippetKind, SnippetTemplate,
+};
 
 pub const DEFAULT_FILE_NAME: &str = "snipdoc.yml";
 
@@ -17,6 +19,16 @@ pub const DEFAULT_FILE_NAME: &str = "snipdoc.yml";
 struct YamlSnippet {
     pub content: String,
     pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nested: Vec<NestedLines>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden: Vec<HiddenLines>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_override: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -52,6 +64,57 @@ impl Yaml {
     }
 }
 
+impl Yaml {
+    /// Returns the content of the file with the snippet key `old` renamed to
+    /// `new`, keeping the formatting and the comments of the file as is.
+    /// Returns `None` when the file has no such snippet.
+    ///
+    /// # Errors
+    ///
+    /// return an error if the file could not be read.
+    pub fn rename(&self, old: &str, new: &str) -> Result<'_, Option<String>> {
+        let content = fs::read_to_string(&self.path)?;
+
+        let mut in_snippets = false;
+        let mut key_indent = None;
+        let mut renamed = false;
+        let mut result = String::with_capacity(content.len());
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            let indent = line.len() - trimmed.len();
+
+            if trimmed.trim().is_empty() || trimmed.starts_with('#') {
+                result.push_str(line);
+                continue;
+            }
+            if indent == 0 {
+                in_snippets = trimmed.starts_with("snippets:");
+                key_indent = None;
+                result.push_str(line);
+                continue;
+            }
+
+            // The snippet ids are the keys at the first indentation level of
+            // the `snippets` section.
+            if in_snippets && *key_indent.get_or_insert(indent) == indent {
+                let key = ["\"", "'", ""].into_iter().find_map(|quote| {
+                    trimmed
+                        .strip_prefix(&format!("{quote}{old}{quote}:"))
+                        .map(|rest| (quote, rest))
+                });
+                if let Some((quote, rest)) = key {
+                    renamed = true;
+                    result.push_str(&format!("{}{quote}{new}{quote}:{rest}", &line[..indent]));
+                    continue;
+                }
+            }
+            result.push_str(line);
+        }
+
+        Ok(renamed.then_some(result))
+    }
+}
+
 impl Db for Yaml {
     /// Load yaml snippets file to `DBData`
     ///
@@ -62,17 +125,27 @@ impl Db for Yaml {
         let yaml_data: Data = serde_yaml::from_reader(std::fs::File::open(&self.path)?)?;
 
         let mut data = DBData {
-            snippets: BTreeMap::new(),
             templates: yaml_data.templates,
+            ..DBData::default()
         };
         for (id, snippet) in &yaml_data.snippets {
-            data.snippets.insert(
-                id.clone(),
+            data.insert(
                 Snippet {
                     id: id.clone(),
                     content: snippet.content.clone(),
                     kind: SnippetKind::Yaml,
                     path: snippet.path.clone(),
+                    nested: snippet.nested.clone(),
+                    hidden: snippet.hidden.clone(),
+                    lines: None,
+                    aliases: snippet.aliases.clone(),
+                    deprecated: snippet.deprecated.clone(),
+                },
+                Definition {
+                    kind: SnippetKind::Yaml,
+                    path: self.path.clone(),
+                    line: None,
+                    allow_override: snippet.allow_override,
                 },
             );
         }
@@ -99,6 +172,11 @@ impl Db for Yaml {
                     YamlSnippet {
                         content: snippet.snippet.join(crate::LINE_ENDING),
                         path: self.path.clone(),
+                        nested: snippet.nested.clone(),
+                        hidden: snippet.hidden.clone(),
+                        allow_override: snippet.allow_override,
+                        aliases: snippet.aliases.clone(),
+                        deprecated: snippet.deprecated.clone(),
                     },
                 );
             }
@@ -117,9 +195,7 @@ impl Db for Yaml {
 
 #[cfg(test)]
 mod tests {
-    use insta::assert_debug_snapshot;
-    #[cfg(not(windows))]
-    use insta::with_settings;
+    use insta::{assert_debug_snapshot, with_settings};
 
     use super::*;
     #[cfg(not(windows))]
@@ -146,7 +222,41 @@ mod tests {
 
         let path = tree_fs::from_yaml_str(yaml_content).unwrap();
         let yaml_db = Yaml::new(path.join("snipdoc-snippets.yaml").as_path());
-        assert_debug_snapshot!(yaml_db.load());
+        with_settings!({filters => {
+           vec![(path.display().to_string().as_str(), "[PATH]")]
+        }}, {
+            assert_debug_snapshot!(yaml_db.load());
+        });
+    }
+
+    #[test]
+    fn can_rename() {
+        let yaml_content = r"
+        files:
+        - path: snipdoc.yml
+          content: |
+            # snippets of the project
+            snippets:
+              install:
+                content: install
+                path: ./snipdoc.yml
+              'other':
+                content: |
+                  install:
+                path: ./snipdoc.yml
+            templates:
+              install:
+                content: '{snippet}'
+        ";
+
+        let path = tree_fs::from_yaml_str(yaml_content).unwrap();
+        let yaml_db = Yaml::new(path.join("snipdoc.yml").as_path());
+
+        assert_debug_snapshot!(yaml_db.rename("install", "installation").unwrap());
+        assert!(yaml_db
+            .rename("not-found", "installation")
+            .unwrap()
+            .is_none());
     }
 
     #[test]
@@ -2,6 +2,14 @@
 //!
 //! This module provides custom error types for parsing and replacing operations
 //! along with result aliases for convenient error handling.
+use std::{
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
 use crate::parser::Rule;
 
 #[derive(thiserror::Error, Debug)]
@@ -18,11 +26,145 @@ pub enum ParseError {
     #[error("{0}")]
     Pest(Box<pest::error::Error<Rule>>),
 
-    #[error(transparent)]
-    HtmlParsing(#[from] scraper::error::SelectorErrorKind<'static>),
+    #[error("{0}")]
+    Diagnostic(Box<Diagnostic>),
+}
+
+/// A line and column in the source, both starting at 1.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Resolves the position of the given byte offset in the input.
+    #[must_use]
+    pub fn from_offset(input: &str, offset: usize) -> Self {
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            line: input[..offset].matches('\n').count() + 1,
+            column: input[line_start..offset].chars().count() + 1,
+        }
+    }
+}
+
+/// A problem found in a file, pointing at the offending tag.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The file in which the problem was found.
+    pub path: PathBuf,
+    /// Describes the problem.
+    pub message: String,
+    /// Where the offending tag starts.
+    pub start: Option<Position>,
+    /// Where the offending tag ends.
+    pub end: Option<Position>,
+    /// The offending tag.
+    pub tag: Option<String>,
+    /// A human hint explaining how the tag is wrong.
+    pub hint: Option<String>,
+    /// The source line in which the offending tag starts.
+    pub excerpt: Option<String>,
+}
+
+impl Diagnostic {
+    #[must_use]
+    pub fn new(message: &str) -> Self {
+        Self {
+            path: PathBuf::new(),
+            message: message.to_string(),
+            start: None,
+            end: None,
+            tag: None,
+            hint: None,
+            excerpt: None,
+        }
+    }
+
+    /// Points the diagnostic at the tag in the given byte range of the input.
+    #[must_use]
+    pub fn at(mut self, input: &str, range: Range<usize>) -> Self {
+        let line_start = input[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[range.start..]
+            .find('\n')
+            .map_or(input.len(), |i| range.start + i);
+
+        self.start = Some(Position::from_offset(input, range.start));
+        self.end = Some(Position::from_offset(input, range.end));
+        self.excerpt = Some(
+            input[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+        );
+        self.tag = Some(input[range].to_string());
+        self
+    }
 
-    #[error("Selector `{selector}` not found in tag: {tag}")]
-    SelectorNotFound { selector: String, tag: String },
+    #[must_use]
+    pub fn with_hint(mut self, hint: &str) -> Self {
+        self.hint = Some(hint.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = path.to_path_buf();
+        self
+    }
+
+    /// Renders the source excerpt with the offending tag underlined and the
+    /// hint next to it.
+    ///
+    /// For example:
+    /// ```text
+    ///   |
+    /// 2 | // <snip id="title">
+    ///   |    ^^^^^^^^^^^^^^^^^ opened here, never closed
+    /// ```
+    #[must_use]
+    pub fn render_excerpt(&self) -> Option<String> {
+        let (start, excerpt) = (self.start?, self.excerpt.as_ref()?);
+
+        let line_len = excerpt.chars().count() + 1;
+        let end_column = self
+            .end
+            .filter(|end| end.line == start.line)
+            .map_or(line_len, |end| end.column);
+        let gutter = " ".repeat(start.line.to_string().len());
+
+        Some(format!(
+            "{gutter} |\n{} | {excerpt}\n{gutter} | {}{} {}",
+            start.line,
+            " ".repeat(start.column - 1),
+            "^".repeat(end_column.saturating_sub(start.column).max(1)),
+            self.hint.as_deref().unwrap_or_default()
+        ))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(start) = self.start {
+            write!(f, "{}:{}: ", start.line, start.column)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        match error {
+            ParseError::Diagnostic(diagnostic) => diagnostic.as_ref().clone(),
+            _ => Self::new(&error.to_string()),
+        }
+    }
+}
+
+impl From<Diagnostic> for ParseError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self::Diagnostic(Box::new(diagnostic))
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -4,14 +4,18 @@ use std::{
     str::FromStr,
 };
 
-use pest::{iterators::Pairs, Parser};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use super::{html_tag, Rule};
+use super::{
+    document::Document,
+    syntax::{Syntax, SyntaxResolver},
+    HiddenLines, LineRange, NestedLines,
+};
 use crate::{
-    errors::ParserResult,
-    parser::{SnippetKind, SnippetParse},
+    config::Config,
+    errors::{Diagnostic, ParserResult, Position},
+    parser::SnippetKind,
     read_file::RFile,
     walk::Walk,
 };
@@ -31,10 +35,33 @@ pub struct CollectSnippet {
     pub tag_close: String,
     /// Hold all the line content inside the snippet.
     pub snippet: Vec<String>,
+    /// The lines of the snippet holding nested snippets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nested: Vec<NestedLines>,
+    /// The lines of the snippet hidden by hide markers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden: Vec<HiddenLines>,
+    /// The lines of the snippet content, unset when the snippet is empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<LineRange>,
+    /// The line of the snippet open tag, starting at 1.
+    #[serde(default)]
+    pub line: usize,
+    /// Defined if `allow_override` attribute exists in the snippet, the
+    /// snippet intentionally shadows other snippets with the same id.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_override: bool,
+    /// Collect the `aliases` attribute, former ids of the snippet.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Collect the `deprecated` attribute if exists in the snippet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
 }
 
 pub struct Collector<'a> {
     pub input: &'a str,
+    pub syntax: &'a Syntax,
 }
 
 /// A struct for collecting snippets from files within a folder.
@@ -42,33 +69,59 @@ pub struct Collector<'a> {
 pub struct CollectSnippetsResults {
     pub root_folder: PathBuf,
     pub snippets: BTreeMap<PathBuf, Vec<CollectSnippet>>,
+    /// Files that could not be read or parsed, their snippets are missing
+    /// from `snippets`.
+    pub errors: BTreeMap<PathBuf, Diagnostic>,
 }
 
 impl<'a> Collector<'a> {
     /// Constructs a new [`ParseFile`] with the provided input.
     #[must_use]
-    pub const fn new(input: &'a str) -> Self {
-        Self { input }
+    pub const fn new(input: &'a str, syntax: &'a Syntax) -> Self {
+        Self { input, syntax }
     }
 
     /// Constructs a `Collector` instance by collecting snippets from files
     /// within the provided `Walk`.
     #[must_use]
-    pub fn walk(walk: &Walk) -> CollectSnippetsResults {
+    pub fn walk(walk: &Walk, config: &Config) -> CollectSnippetsResults {
         let files = walk.get_files();
 
         tracing::debug!(
             count_files = files.len(),
             "start collect snippets from code"
         );
-        let snippets = files
+        let resolver = SyntaxResolver::new(config);
+        let results = files
             .par_iter()
-            .flat_map(|path| Self::file(path.as_path()).map(|findings| (path.clone(), findings)))
-            .collect::<BTreeMap<_, _>>();
+            .map(|path| {
+                let syntax =
+                    resolver.resolve(path.strip_prefix(&walk.folder).unwrap_or(path));
+                let result = Self::file(path.as_path(), &syntax).map_err(|err| {
+                    tracing::debug!(path = %path.display(), err = %err, "could not collect snippets");
+                    Diagnostic::from(&err).with_path(path)
+                });
+                (path.clone(), result)
+            })
+            .collect::<Vec<_>>();
+
+        let mut snippets = BTreeMap::new();
+        let mut errors = BTreeMap::new();
+        for (path, result) in results {
+            match result {
+                Ok(findings) => {
+                    snippets.insert(path, findings);
+                }
+                Err(diagnostic) => {
+                    errors.insert(path, diagnostic);
+                }
+            }
+        }
 
         CollectSnippetsResults {
             root_folder: walk.folder.clone(),
             snippets,
+            errors,
         }
     }
 
@@ -79,13 +132,13 @@ impl<'a> Collector<'a> {
     /// Returns `Some` containing the collected snippets if successful,
     /// otherwise returns `None`.
     #[allow(clippy::missing_errors_doc)]
-    pub fn file(path: &Path) -> ParserResult<'_, Vec<CollectSnippet>> {
+    pub fn file(path: &Path, syntax: &Syntax) -> ParserResult<'static, Vec<CollectSnippet>> {
         let span = tracing::info_span!("parse_file", path = %path.display());
         let _guard = span.enter();
 
         let r_file = RFile::new(path)?;
 
-        Collector::new(&r_file.content).run()
+        Collector::new(&r_file.content, syntax).run()
     }
 
     /// Parses the input file content and extracts snippets.
@@ -94,98 +147,87 @@ impl<'a> Collector<'a> {
     ///
     /// This function may return an error if it fails to parse the input file.
     /// Other errors encountered during parsing will be logged.
-    fn run(&self) -> ParserResult<'_, Vec<CollectSnippet>> {
-        let pairs = SnippetParse::parse(Rule::file, self.input)?;
-
-        let mut findings: Vec<CollectSnippet> = vec![];
-        Self::collect_snippets(pairs, &mut findings);
-        Ok(findings)
-    }
-
-    /// Recursively collects snippets from the provided pairs and populates the
-    /// given vector with the snippets.
-    ///
-    /// This function recursively traverses the pairs, extracting snippets and
-    /// their attributes.
     ///
     /// # Panics
     ///
-    /// This function assumes that the parsing configuration always captures a
-    /// snippet containing a tag open. If this assumption is violated, it
-    /// indicates a misconfiguration or a critical issue in the parser's
-    /// behavior. Consequently, in production code, encountering this panic
-    /// indicates a severe problem that requires immediate attention.
-    /// In testing scenarios, this panic should be captured to ensure the
-    /// correctness of the parser.
-    fn collect_snippets(pairs: Pairs<'_, Rule>, snippets: &mut Vec<CollectSnippet>) {
-        if pairs.len() == 0 {
-            return;
-        }
+    /// The parser only captures tags containing an `id` attribute. A snippet
+    /// without an id indicates a critical issue in the parser's behavior.
+    fn run(&self) -> ParserResult<'_, Vec<CollectSnippet>> {
+        let document = Document::parse(self.input, self.syntax)?;
 
-        for pair in pairs {
-            let inner = pair.clone().into_inner();
-
-            match pair.as_rule() {
-                Rule::snippet => {
-                    let children: Pairs<'_, Rule> = pair.clone().into_inner();
-                    let tag_open = html_tag::get_tag_open(&children);
-                    let tag_close = html_tag::get_tag_close(children.clone());
-
-                    let attributes = match html_tag::get_tag_attributes(tag_open) {
-                        Ok(attributes) => attributes,
-                        Err(err) => {
-                            tracing::debug!(tag_open, err = %err, "could not extract attributes from the tag");
-                            continue;
-                        }
-                    };
-
-                    tracing::debug!(
-                        tag_open,
-                        tag_close,
-                        attributes = format!("{:#?}", attributes),
-                        "found attributes"
-                    );
-
-                    let mut lines = pair
-                        .as_str()
-                        .lines()
-                        .map(std::string::ToString::to_string)
-                        .skip(1)
-                        .collect::<Vec<_>>();
-
-                    lines.pop();
-
-                    if let Some(last) = lines.last() {
-                        if last == &tag_close.replace('\n', "") {
-                            // TODO:: replace \n to crate::LINE_ENDING
-                            lines.pop();
-                        }
-                    }
-
-                    snippets.push(CollectSnippet {
-                        // Attribute ID as part of the parser configuration is
-                        // mandatory. the snippet should't be captured if id
-                        // element is not present. In this case
-                        // user `expect` should brake the parser.
-                        id: attributes
-                            .get("id")
-                            .expect("assertion fails, snippet without element id")
-                            .to_string(),
-                        inject_from: attributes
-                            .get("inject_from")
-                            .and_then(|k| SnippetKind::from_str(k).ok()),
-                        tag_open: tag_open.to_string(),
-                        tag_close: tag_close.to_string(),
-                        snippet: lines,
-                    });
-
-                    Self::collect_snippets(children, snippets);
-                }
-                _ => {
-                    Self::collect_snippets(inner.clone(), snippets);
+        let mut findings: Vec<CollectSnippet> = vec![];
+        for block in document
+            .blocks
+            .iter()
+            .filter(|block| !block.is_placeholder())
+        {
+            let tag_open = document.text(block.open.tag.span.clone());
+            let tag_close = document.text(block.close.span.clone());
+            let attributes = block.open.tag.attributes_map();
+
+            tracing::debug!(
+                tag_open,
+                tag_close,
+                attributes = format!("{:#?}", attributes),
+                "found attributes"
+            );
+
+            // Attribute ID as part of the parser configuration is
+            // mandatory. the snippet should't be captured if id
+            // element is not present. In this case
+            // user `expect` should brake the parser.
+            let id = attributes
+                .get("id")
+                .expect("assertion fails, snippet without element id");
+            let inject_from = attributes
+                .get("inject_from")
+                .and_then(|k| SnippetKind::from_str(k).ok());
+
+            // Placeholders keep the id they refer to, snippets are named in
+            // their namespace.
+            let namespace = attributes.get("namespace").map(String::as_str);
+            let is_placeholder = inject_from.is_some();
+            let qualify = |id: &str| {
+                if is_placeholder {
+                    id.to_string()
+                } else {
+                    self.syntax.qualify(id, namespace)
                 }
-            }
+            };
+
+            findings.push(CollectSnippet {
+                id: qualify(id),
+                inject_from,
+                tag_open: tag_open.to_string(),
+                tag_close: tag_close.to_string(),
+                snippet: document
+                    .content_lines(block)
+                    .into_iter()
+                    .map(std::string::ToString::to_string)
+                    .collect(),
+                nested: document.nested_lines(block),
+                // Placeholders are replaced on injection, their content is
+                // not a snippet.
+                hidden: if is_placeholder {
+                    vec![]
+                } else {
+                    document.hidden_lines(block)?
+                },
+                lines: document.content_line_range(block),
+                line: Position::from_offset(self.input, block.open.span.start).line,
+                allow_override: attributes.contains_key("allow_override"),
+                aliases: attributes.get("aliases").map_or_else(Vec::new, |aliases| {
+                    aliases
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|alias| !alias.is_empty())
+                        .map(qualify)
+                        .collect()
+                }),
+                deprecated: attributes.get("deprecated").cloned(),
+            });
         }
+        Ok(findings)
     }
 }
 
@@ -194,6 +236,7 @@ mod tests {
     use insta::assert_debug_snapshot;
 
     use super::*;
+    use crate::config::CommentToken;
 
     #[test]
     fn can_collect() {
@@ -230,7 +273,7 @@ hashtag
 # </snip>
 
 # Inner snippets
-<!-- <snip id="level-1" -->
+<!-- <snip id="level-1"> -->
 Level 1
 // <snip id="level-2">
 Level 2
@@ -241,7 +284,130 @@ Level 3
 <!-- </snip> -->
 "#;
 
-        let collector = Collector::new(content);
+        let syntax = Syntax::default();
+        let collector = Collector::new(content, &syntax);
+        assert_debug_snapshot!(collector.run());
+    }
+
+    #[test]
+    fn can_walk_with_errors() {
+        let yaml_content = r#"
+        files:
+        - path: valid.rs
+          content: |
+            // <snip id="valid">
+            // </snip>
+        - path: invalid.rs
+          content: |
+            // <snip id="invalid">
+        "#;
+
+        let root_path = tree_fs::from_yaml_str(yaml_content).unwrap();
+        let walk = Walk::new(&root_path).unwrap();
+        let results = Collector::walk(&walk, &Config::default());
+
+        assert_eq!(
+            results.snippets.keys().collect::<Vec<_>>(),
+            vec![&walk.folder.join("valid.rs")]
+        );
+        let diagnostic = results.errors.get(&walk.folder.join("invalid.rs")).unwrap();
+        assert_eq!(diagnostic.path, walk.folder.join("invalid.rs"));
+        assert_eq!(diagnostic.tag.as_deref(), Some("<snip id=\"invalid\">"));
+    }
+
+    #[test]
+    fn can_collect_from_block_comments() {
+        let content = r#"/* <snip id="c-style"> */
+int main() { return 0; }
+/* </snip> */
+
+/** <snip id="doc-style"> */
+public class Main {}
+/** </snip> */
+
+(* <snip id="ocaml-style"> *)
+let () = print_endline "snipdoc"
+(* </snip> *)
+
+/*<snip id="no-spaces">*/
+.snipdoc { color: red; }
+/*</snip>*/
+"#;
+
+        let syntax = Syntax::default();
+        let collector = Collector::new(content, &syntax);
+        assert_debug_snapshot!(collector.run());
+    }
+
+    #[test]
+    fn can_collect_with_custom_comments() {
+        let content = r#"-- <snip id="sql">
+SELECT 1;
+-- </snip>
+
+{# <snip id="jinja"> #}
+{{ title }}
+{# </snip> #}
+
+REM <snip id="batch">
+echo snipdoc
+REM </snip>
+"#;
+
+        let syntax = Syntax {
+            comments: vec![
+                CommentToken::line("--"),
+                CommentToken::block("{#", "#}"),
+                CommentToken::line("REM"),
+            ],
+            ..Syntax::default()
+        };
+        let collector = Collector::new(content, &syntax);
+        assert_debug_snapshot!(collector.run());
+    }
+
+    #[test]
+    fn can_collect_overlapping_snippets() {
+        let content = r#"// <snip id="setup">
+let config = Config::default();
+// <snip id="usage">
+let client = Client::new(config);
+// </snip id="setup">
+client.run();
+// </snip id="usage">
+"#;
+
+        let syntax = Syntax::default();
+        let collector = Collector::new(content, &syntax);
+        assert_debug_snapshot!(collector.run());
+    }
+
+    #[test]
+    fn can_collect_inline_snippets() {
+        let content = r#"pub const VERSION: &str = /*<snip id="version">*/"1.2.0"/*</snip>*/;
+"#;
+
+        let syntax = Syntax::default();
+        let collector = Collector::new(content, &syntax);
+        assert_debug_snapshot!(collector.run());
+    }
+
+    #[test]
+    fn can_collect_bare_tags() {
+        let content = r#"def main():
+    """
+    <snip id="docstring">
+    Run the main entrypoint.
+    </snip>
+    Text mentioning <snip id="not-a-tag"> inline is ignored.
+    """
+"#;
+
+        let syntax = Syntax {
+            bare: true,
+            ..Syntax::default()
+        };
+        let collector = Collector::new(content, &syntax);
         assert_debug_snapshot!(collector.run());
     }
 }
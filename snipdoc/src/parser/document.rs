@@ -0,0 +1,1015 @@
+//! Locates snip tags in a document and pairs them into snippet blocks.
+//!
+//! The grammar only describes the tags themselves, the comment tokens wrapping
+//! them depend on the file type and are resolved by [`Syntax`].
+use std::ops::Range;
+
+use super::{markdown, syntax::Syntax, tag::Tag, HiddenLines, LineRange, LineSpan, NestedLines};
+use crate::{
+    config::CommentToken,
+    errors::{Diagnostic, ParserResult, Position},
+};
+
+/// A snip tag together with the comment tokens wrapping it.
+#[derive(Debug, Clone)]
+pub struct Marker {
+    /// The whole marker, including the wrapping comment tokens.
+    pub span: Range<usize>,
+    /// The snip tag only, e.g. `<snip id="x">` or `</snip>`.
+    pub tag: Tag,
+}
+
+impl Marker {
+    /// The snippet id of the tag, optional for close tags.
+    #[must_use]
+    pub fn id(&self) -> Option<&str> {
+        self.tag.id()
+    }
+}
+
+/// A snippet region delimited by an open and a close marker.
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub open: Marker,
+    pub close: Marker,
+    /// Nesting level of the block, `0` for top level blocks.
+    pub depth: usize,
+}
+
+impl Block {
+    /// The range of the whole block, from the open marker to the close
+    /// marker.
+    #[must_use]
+    pub const fn span(&self) -> Range<usize> {
+        self.open.span.start..self.close.span.end
+    }
+
+    /// The range between the open and the close marker, empty for a
+    /// placeholder.
+    #[must_use]
+    pub fn inner(&self) -> Range<usize> {
+        self.open.span.end..self.close.span.start.max(self.open.span.end)
+    }
+
+    /// Whether the block is a self-closing placeholder. Its open and close
+    /// markers are the same.
+    #[must_use]
+    pub const fn is_placeholder(&self) -> bool {
+        self.open.tag.self_closing
+    }
+}
+
+/// A parsed document holding all the snippet blocks found in the input.
+#[derive(Debug)]
+pub struct Document<'a> {
+    pub input: &'a str,
+    /// All the blocks, ordered by the position of their open marker.
+    pub blocks: Vec<Block>,
+    /// The element name of the tags.
+    pub keyword: String,
+}
+
+impl<'a> Document<'a> {
+    /// Parses the input and pairs every open marker with its close marker.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when an open marker is never closed or when a close
+    /// marker has no matching open marker.
+    pub fn parse(input: &'a str, syntax: &Syntax) -> ParserResult<'a, Self> {
+        let mut blocks = vec![];
+        // Open markers waiting for their close marker. The flag is set when a
+        // named close marker closed an outer block first, such overlapping
+        // blocks must be closed by name as well.
+        let mut stack: Vec<(Marker, bool)> = vec![];
+
+        // `pos` is where the search continues, `marker_end` is the end of the
+        // last marker found, a comment token can't start before it.
+        let mut pos = 0;
+        let mut marker_end = 0;
+
+        // Tags inside markdown code are examples, not snippets.
+        let code = if syntax.markdown {
+            markdown::code_ranges(input, syntax.fenced_examples)
+        } else {
+            vec![]
+        };
+        let mut code = code.iter().peekable();
+
+        while let Some(offset) = input[pos..].find('<') {
+            let at = pos + offset;
+
+            while code.next_if(|range| range.end <= at).is_some() {}
+            if let Some(range) = code.peek().filter(|range| range.contains(&at)) {
+                pos = range.end;
+                continue;
+            }
+
+            // An escaped tag, e.g. `\<snip id="x">`, is kept as text.
+            if input[..at].ends_with('\\') {
+                pos = at + 1;
+                continue;
+            }
+
+            // A raw region, e.g. `<snip-raw>`, is never parsed for tags.
+            if input[at..].starts_with(&format!("<{}-raw>", syntax.keyword)) {
+                pos = Self::raw_region_end(input, at, &syntax.keyword)?;
+                marker_end = pos;
+                continue;
+            }
+
+            if let Some(open) = Self::open_marker(input, marker_end, at, syntax)? {
+                pos = open.span.end;
+                marker_end = pos;
+                if open.tag.self_closing {
+                    blocks.push(Block {
+                        open: open.clone(),
+                        close: open,
+                        depth: stack.len(),
+                    });
+                } else {
+                    stack.push((open, false));
+                }
+            } else if let Some(close) = Self::close_marker(input, marker_end, at, syntax)? {
+                pos = close.span.end;
+                marker_end = pos;
+
+                let index = Self::find_open(input, &stack, &close, &syntax.keyword)?;
+                let (open, _) = stack.remove(index);
+                for (_, crossed) in &mut stack[index..] {
+                    *crossed = true;
+                }
+
+                // The markers left below the index were opened before this
+                // block and are still open, so they contain it.
+                blocks.push(Block {
+                    open,
+                    close,
+                    depth: index,
+                });
+            } else {
+                pos = at + 1;
+            }
+        }
+
+        if let Some((open, _)) = stack.pop() {
+            return Err(Diagnostic::new("tag is never closed")
+                .at(input, open.tag.span)
+                .with_hint("opened here, never closed")
+                .into());
+        }
+
+        blocks.sort_by_key(|block| block.open.span.start);
+        Ok(Self {
+            input,
+            blocks,
+            keyword: syntax.keyword.clone(),
+        })
+    }
+
+    /// Returns the end of the raw region opened at `at`.
+    fn raw_region_end(input: &str, at: usize, keyword: &str) -> ParserResult<'a, usize> {
+        let raw_close = format!("</{keyword}-raw>");
+        input[at..]
+            .find(&raw_close)
+            .map(|end| at + end + raw_close.len())
+            .ok_or_else(|| {
+                Diagnostic::new("raw region is never closed")
+                    .at(input, at..at + raw_close.len() - 1)
+                    .with_hint(&format!("opened here, close it with `{raw_close}`"))
+                    .into()
+            })
+    }
+
+    /// Finds the index in the stack of the open marker that the given close
+    /// marker closes.
+    ///
+    /// A named close marker closes the latest open marker with the same id,
+    /// an unnamed close marker closes the latest open marker.
+    fn find_open(
+        input: &str,
+        stack: &[(Marker, bool)],
+        close: &Marker,
+        keyword: &str,
+    ) -> ParserResult<'a, usize> {
+        let latest_id = stack
+            .last()
+            .and_then(|(open, _)| open.id())
+            .unwrap_or_default();
+
+        match close.id() {
+            Some(id) => stack
+                .iter()
+                .rposition(|(open, _)| open.id() == Some(id))
+                .ok_or_else(|| {
+                    let diagnostic =
+                        Diagnostic::new(&format!("close tag of `{id}` has no matching open tag"));
+                    let hint = if stack.is_empty() {
+                        "closed here, never opened".to_string()
+                    } else {
+                        format!("`{id}` is not open, the latest open snippet is `{latest_id}`")
+                    };
+                    diagnostic
+                        .at(input, close.tag.span.clone())
+                        .with_hint(&hint)
+                        .into()
+                }),
+            None => match stack.last() {
+                None => Err(Diagnostic::new("close tag has no matching open tag")
+                    .at(input, close.tag.span.clone())
+                    .with_hint("closed here, never opened")
+                    .into()),
+                Some((_, true)) => Err(Diagnostic::new(&format!(
+                    "close tag of the overlapping snippet `{latest_id}` must be named"
+                ))
+                .at(input, close.tag.span.clone())
+                .with_hint(&format!("use `</{keyword} id=\"{latest_id}\">`"))
+                .into()),
+                Some(_) => Ok(stack.len() - 1),
+            },
+        }
+    }
+
+    /// Returns the text of the given range.
+    #[must_use]
+    pub fn text(&self, range: Range<usize>) -> &'a str {
+        &self.input[range]
+    }
+
+    /// Checks whether the block is an inline snippet, e.g.
+    /// `v<!--<snip id="version">-->1.2.0<!--</snip>-->`.
+    ///
+    /// A block is inline when both markers are on the same line and they share
+    /// it with other text. Markers alone on a line, even empty, form a line
+    /// based block.
+    #[must_use]
+    pub fn is_inline(&self, block: &Block) -> bool {
+        if block.is_placeholder() || self.text(block.inner()).contains('\n') {
+            return false;
+        }
+
+        let line_start = self.input[..block.open.span.start]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let line_end = self.input[block.close.span.end..]
+            .find('\n')
+            .map_or(self.input.len(), |i| block.close.span.end + i);
+
+        !self
+            .text(line_start..block.open.span.start)
+            .trim()
+            .is_empty()
+            || !self.text(block.inner()).trim().is_empty()
+            || !self.text(block.close.span.end..line_end).trim().is_empty()
+    }
+
+    /// Returns the lines between the open and the close marker, without the
+    /// lines holding the markers. An inline block has a single line holding
+    /// its exact content.
+    #[must_use]
+    pub fn content_lines(&self, block: &Block) -> Vec<&'a str> {
+        self.content_ranges(block)
+            .into_iter()
+            .map(|range| self.text(range))
+            .collect()
+    }
+
+    /// Returns the lines of the input holding [`Self::content_lines`], `None`
+    /// when the block has no content lines.
+    #[must_use]
+    pub fn content_line_range(&self, block: &Block) -> Option<LineRange> {
+        let lines = self.content_ranges(block);
+        let (first, last) = (lines.first()?, lines.last()?);
+        Some(LineRange {
+            start: Position::from_offset(self.input, first.start).line,
+            end: Position::from_offset(self.input, last.start).line,
+        })
+    }
+
+    /// Returns the lines of [`Self::content_lines`] holding the markers of
+    /// other blocks, such as nested snippets, with the span of the markers in
+    /// their line.
+    #[must_use]
+    pub fn nested_lines(&self, block: &Block) -> Vec<NestedLines> {
+        let lines = self.content_ranges(block);
+        let span_of = |marker: &Marker| {
+            lines
+                .iter()
+                .position(|line| marker.span.start < line.end && marker.span.end > line.start)
+                .map(|index| {
+                    let line = &lines[index];
+                    LineSpan {
+                        line: index,
+                        start: marker.span.start.max(line.start) - line.start,
+                        end: marker.span.end.min(line.end) - line.start,
+                    }
+                })
+        };
+
+        self.blocks
+            .iter()
+            .filter(|other| other.span() != block.span())
+            .filter_map(|other| {
+                let open = span_of(&other.open);
+                // A placeholder has a single marker.
+                let close = span_of(&other.close).filter(|_| !other.is_placeholder());
+                match (open, close) {
+                    (Some(open), Some(close)) => Some(NestedLines {
+                        open: open.line,
+                        close: close.line,
+                        markers: vec![open, close],
+                    }),
+                    // Only one marker of an overlapping block is in this block.
+                    (Some(marker), None) | (None, Some(marker)) => Some(NestedLines {
+                        open: marker.line,
+                        close: marker.line,
+                        markers: vec![marker],
+                    }),
+                    (None, None) => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the lines of [`Self::content_lines`] hidden by the line
+    /// markers `snip:hide`, `snip:hide-start` / `snip:hide-end` and
+    /// `snip:...`, named after the keyword of the document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when a `hide-start` marker is never closed or a
+    /// `hide-end` marker has no matching `hide-start`.
+    pub fn hidden_lines(&self, block: &Block) -> ParserResult<'a, Vec<HiddenLines>> {
+        let mut hidden = vec![];
+        // The line and the marker range of the region being hidden.
+        let mut start: Option<(usize, Range<usize>)> = None;
+
+        for (index, line) in self.content_ranges(block).into_iter().enumerate() {
+            let Some((marker, at)) = LineMarker::find(self.text(line.clone()), &self.keyword)
+            else {
+                continue;
+            };
+            let marker_range = line.start + at..line.start + at + marker.len(&self.keyword);
+
+            match (marker, &start) {
+                (LineMarker::HideStart, Some((_, open))) => {
+                    return Err(Diagnostic::new(&format!(
+                        "`{}:hide-start` inside a hidden region",
+                        self.keyword
+                    ))
+                    .at(self.input, marker_range)
+                    .with_hint(&format!(
+                        "close the region opened at line {} first",
+                        Position::from_offset(self.input, open.start).line
+                    ))
+                    .into());
+                }
+                (LineMarker::HideStart, None) => start = Some((index, marker_range)),
+                (LineMarker::HideEnd, Some((from, _))) => {
+                    hidden.push(HiddenLines {
+                        start: *from,
+                        end: index,
+                        ellipsis: None,
+                    });
+                    start = None;
+                }
+                (LineMarker::HideEnd, None) => {
+                    return Err(Diagnostic::new(&format!(
+                        "`{}:hide-end` has no matching `{}:hide-start`",
+                        self.keyword, self.keyword
+                    ))
+                    .at(self.input, marker_range)
+                    .into());
+                }
+                (_, Some(_)) => (),
+                (LineMarker::Hide, None) => hidden.push(HiddenLines {
+                    start: index,
+                    end: index,
+                    ellipsis: None,
+                }),
+                (LineMarker::Ellipsis, None) => hidden.push(HiddenLines {
+                    start: index,
+                    end: index,
+                    ellipsis: Some(LineMarker::ellipsis(
+                        self.text(line.clone()),
+                        at,
+                        &self.keyword,
+                    )),
+                }),
+            }
+        }
+
+        if let Some((_, open)) = start {
+            return Err(
+                Diagnostic::new(&format!("`{}:hide-start` is never closed", self.keyword))
+                    .at(self.input, open)
+                    .with_hint(&format!(
+                        "add a `{}:hide-end` line before the end of the snippet",
+                        self.keyword
+                    ))
+                    .into(),
+            );
+        }
+
+        Ok(hidden)
+    }
+
+    fn content_ranges(&self, block: &Block) -> Vec<Range<usize>> {
+        let inner = block.inner();
+        if self.is_inline(block) {
+            return vec![inner];
+        }
+
+        let newlines = self
+            .text(inner.clone())
+            .match_indices('\n')
+            .map(|(i, _)| inner.start + i)
+            .collect::<Vec<_>>();
+
+        newlines
+            .windows(2)
+            .map(|pair| {
+                let line = pair[0] + 1..pair[1];
+                if self.text(line.clone()).ends_with('\r') {
+                    line.start..line.end - 1
+                } else {
+                    line
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the indentation in front of the close marker of the block.
+    #[must_use]
+    pub fn close_indent(&self, block: &Block) -> &'a str {
+        let inner = self.text(block.inner());
+        let last_line = inner.rsplit('\n').next().unwrap_or_default();
+        if inner.contains('\n') && last_line.trim().is_empty() {
+            last_line
+        } else {
+            ""
+        }
+    }
+
+    /// Expands a placeholder into an open and a close marker using the same
+    /// comment style, along with the indentation of the close marker.
+    #[must_use]
+    pub fn expand_placeholder(&self, block: &Block) -> (String, &'a str, String) {
+        let marker = &block.open;
+        let comment_open = self.text(marker.span.start..marker.tag.span.start);
+        let comment_close = self.text(marker.tag.span.end..marker.span.end);
+
+        let line_start = self.input[..marker.span.start]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let indent = self.text(line_start..marker.span.start);
+        let indent = if indent.trim().is_empty() { indent } else { "" };
+
+        (
+            format!(
+                "{comment_open}{}{comment_close}",
+                marker.tag.open_text(self.input)
+            ),
+            indent,
+            format!("{comment_open}</{}>{comment_close}", self.keyword),
+        )
+    }
+
+    fn open_marker(
+        input: &str,
+        min: usize,
+        at: usize,
+        syntax: &Syntax,
+    ) -> ParserResult<'a, Option<Marker>> {
+        let Some((comment_open, token)) = leading_comment(input, min, at, syntax) else {
+            return Self::bare_marker(input, at, syntax);
+        };
+        let Some(tag) = Tag::parse_open(input, at, &syntax.keyword)? else {
+            return Ok(None);
+        };
+        let comment_close = trailing_comment(input, tag.span.end, token);
+
+        Ok(Some(Marker {
+            span: comment_open.start..comment_close.map_or(tag.span.end, |c| c.end),
+            tag,
+        }))
+    }
+
+    /// Recognizes an open tag without a comment token when the syntax allows
+    /// bare tags and the tag is alone on its line.
+    fn bare_marker(input: &str, at: usize, syntax: &Syntax) -> ParserResult<'a, Option<Marker>> {
+        if !syntax.bare {
+            return Ok(None);
+        }
+
+        let line_start = input[..at].rfind('\n').map_or(0, |i| i + 1);
+        if !input[line_start..at].trim().is_empty() {
+            return Ok(None);
+        }
+        let Some(tag) = Tag::parse_open(input, at, &syntax.keyword)? else {
+            return Ok(None);
+        };
+        let rest = &input[tag.span.end..];
+        if !rest[..rest.find('\n').unwrap_or(rest.len())]
+            .trim()
+            .is_empty()
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(Marker {
+            span: tag.span.clone(),
+            tag,
+        }))
+    }
+
+    fn close_marker(
+        input: &str,
+        min: usize,
+        at: usize,
+        syntax: &Syntax,
+    ) -> ParserResult<'a, Option<Marker>> {
+        let leading = leading_comment(input, min, at, syntax);
+        // A malformed close tag is only reported when it's inside a comment,
+        // elsewhere it's likely plain text.
+        let tag = match Tag::parse_close(input, at, &syntax.keyword) {
+            Ok(Some(tag)) => tag,
+            Err(err) if leading.is_some() => return Err(err),
+            _ => return Ok(None),
+        };
+        let comment_close = leading
+            .as_ref()
+            .and_then(|(_, token)| trailing_comment(input, tag.span.end, token));
+
+        Ok(Some(Marker {
+            span: leading.map_or(tag.span.start, |(c, _)| c.start)
+                ..comment_close.map_or(tag.span.end, |c| c.end),
+            tag,
+        }))
+    }
+}
+
+/// A marker hiding lines of a snippet, e.g. `// snip:hide`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineMarker {
+    /// Hides the line.
+    Hide,
+    /// Hides the lines up to the `HideEnd` marker, both marker lines
+    /// included.
+    HideStart,
+    HideEnd,
+    /// Replaces the line with an ellipsis.
+    Ellipsis,
+}
+
+impl LineMarker {
+    /// The markers, longer names first since `hide` is a prefix of the
+    /// others.
+    const ALL: [(Self, &'static str); 4] = [
+        (Self::HideStart, "hide-start"),
+        (Self::HideEnd, "hide-end"),
+        (Self::Hide, "hide"),
+        (Self::Ellipsis, "..."),
+    ];
+
+    /// Finds the marker in the line, with its offset.
+    fn find(line: &str, keyword: &str) -> Option<(Self, usize)> {
+        let prefix = format!("{keyword}:");
+        line.match_indices(&prefix)
+            .filter(|(at, _)| {
+                // `snip:` must not be the end of a longer word.
+                !line[..*at]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            })
+            .find_map(|(at, _)| {
+                let rest = &line[at + prefix.len()..];
+                Self::ALL
+                    .iter()
+                    .find(|(_, name)| {
+                        rest.strip_prefix(name).is_some_and(|after| {
+                            !after
+                                .chars()
+                                .next()
+                                .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-')
+                        })
+                    })
+                    .map(|(marker, _)| (*marker, at))
+            })
+    }
+
+    /// The length of the marker text, e.g. `snip:hide`.
+    fn len(self, keyword: &str) -> usize {
+        let name = Self::ALL
+            .iter()
+            .find(|(marker, _)| *marker == self)
+            .map_or("", |(_, name)| name);
+        keyword.len() + 1 + name.len()
+    }
+
+    /// The line replacing a line holding an `Ellipsis` marker at `at`, keeping
+    /// its indentation and the comment token in front of the marker, e.g.
+    /// `    setup(); // snip:...` becomes `    // ...`.
+    fn ellipsis(line: &str, at: usize, keyword: &str) -> String {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let token = line[..at]
+            .split_whitespace()
+            .next_back()
+            .filter(|token| !token.chars().any(char::is_alphanumeric));
+        let after = &line[at + Self::Ellipsis.len(keyword)..];
+
+        match token {
+            Some(token) => format!("{indent}{token} ...{after}"),
+            None => format!("{indent}...{after}"),
+        }
+        .trim_end()
+        .to_string()
+    }
+}
+
+/// Finds the comment token in front of the tag at `at`, on the same line and
+/// not before `min`.
+fn leading_comment<'s>(
+    input: &str,
+    min: usize,
+    at: usize,
+    syntax: &'s Syntax,
+) -> Option<(Range<usize>, &'s CommentToken)> {
+    let line_start = input[..at].rfind('\n').map_or(0, |i| i + 1).max(min);
+    let before = input[line_start..at].trim_end_matches([' ', '\t']);
+    let token = syntax.comment_ending(before)?;
+    let end = line_start + before.len();
+
+    Some((end - token.open.len()..end, token))
+}
+
+/// Finds the closing token of the comment right after the tag ending at
+/// `from`.
+fn trailing_comment(input: &str, from: usize, token: &CommentToken) -> Option<Range<usize>> {
+    let close = token.close.as_deref()?;
+    let rest = &input[from..];
+    let trimmed = rest.trim_start_matches([' ', '\t']);
+    let start = from + rest.len() - trimmed.len();
+
+    trimmed
+        .starts_with(close)
+        .then(|| start..start + close.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{ParseError, Position};
+
+    #[test]
+    fn can_pair_nested_blocks() {
+        let content = r#"<!-- <snip id="outer"> -->
+// <snip id="inner">
+inner
+// </snip>
+<!-- </snip> -->
+"#;
+        let document = Document::parse(content, &Syntax::default()).unwrap();
+
+        let blocks = document
+            .blocks
+            .iter()
+            .map(|block| {
+                (
+                    document.text(block.open.span.clone()),
+                    document.text(block.close.span.clone()),
+                    block.depth,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            blocks,
+            vec![
+                ("<!-- <snip id=\"outer\"> -->", "<!-- </snip> -->", 0),
+                ("// <snip id=\"inner\">", "// </snip>", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn fail_on_close_tag_without_open() {
+        let content = "<snip id=\"no-comment\">\n</snip>\n";
+
+        let Err(ParseError::Diagnostic(diagnostic)) = Document::parse(content, &Syntax::default())
+        else {
+            panic!("expected a diagnostic");
+        };
+        assert_eq!(diagnostic.start, Some(Position { line: 2, column: 1 }));
+        assert_eq!(diagnostic.tag.as_deref(), Some("</snip>"));
+    }
+
+    #[test]
+    fn fail_on_unclosed_tag() {
+        let content = "line\n# <snip id=\"unclosed\">\n";
+
+        let Err(ParseError::Diagnostic(diagnostic)) = Document::parse(content, &Syntax::default())
+        else {
+            panic!("expected a diagnostic");
+        };
+        assert_eq!(
+            diagnostic.render_excerpt().unwrap(),
+            "  |\n2 | # <snip id=\"unclosed\">\n  |   ^^^^^^^^^^^^^^^^^^^^ opened here, never closed"
+        );
+    }
+
+    #[test]
+    fn can_pair_named_close_tags() {
+        let content = r#"// <snip id="a">
+// <snip id="b">
+// </snip id="b">
+// <snip id="c">
+// </snip id="a">
+// </snip id="c">
+"#;
+        let document = Document::parse(content, &Syntax::default()).unwrap();
+
+        let blocks = document
+            .blocks
+            .iter()
+            .map(|block| {
+                (
+                    block.open.id(),
+                    document.text(block.close.tag.span.clone()),
+                    block.depth,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            blocks,
+            vec![
+                (Some("a"), "</snip id=\"a\">", 0),
+                (Some("b"), "</snip id=\"b\">", 1),
+                (Some("c"), "</snip id=\"c\">", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn fail_on_mismatched_close_tag() {
+        let content = "// <snip id=\"a\">\n// </snip id=\"b\">\n";
+
+        let Err(ParseError::Diagnostic(diagnostic)) = Document::parse(content, &Syntax::default())
+        else {
+            panic!("expected a diagnostic");
+        };
+        assert_eq!(
+            diagnostic.message,
+            "close tag of `b` has no matching open tag"
+        );
+        assert_eq!(
+            diagnostic.hint.as_deref(),
+            Some("`b` is not open, the latest open snippet is `a`")
+        );
+    }
+
+    #[test]
+    fn fail_on_unnamed_close_of_overlapping_tag() {
+        let content = r#"// <snip id="a">
+// <snip id="b">
+// </snip id="a">
+// </snip>
+"#;
+
+        let Err(ParseError::Diagnostic(diagnostic)) = Document::parse(content, &Syntax::default())
+        else {
+            panic!("expected a diagnostic");
+        };
+        assert_eq!(diagnostic.start, Some(Position { line: 4, column: 4 }));
+        assert_eq!(
+            diagnostic.message,
+            "close tag of the overlapping snippet `b` must be named"
+        );
+    }
+
+    #[test]
+    fn can_parse_tag_with_quoted_brackets() {
+        let content = r#"<!-- <snip id="docs.setup/unix:sh" template='<details>{snippet}</details>'> -->
+<!-- </snip id="docs.setup/unix:sh"> -->
+"#;
+        let document = Document::parse(content, &Syntax::default()).unwrap();
+
+        let block = &document.blocks[0];
+        assert_eq!(block.open.id(), Some("docs.setup/unix:sh"));
+        assert_eq!(
+            document.text(block.open.span.clone()),
+            content.lines().next().unwrap()
+        );
+    }
+
+    #[test]
+    fn can_parse_placeholder() {
+        let content = r#"text
+  <!-- <snip id="x" inject_from="code"/> -->
+"#;
+        let document = Document::parse(content, &Syntax::default()).unwrap();
+
+        let block = &document.blocks[0];
+        assert!(block.is_placeholder());
+        assert!(document.content_lines(block).is_empty());
+        assert_eq!(
+            document.expand_placeholder(block),
+            (
+                r#"<!-- <snip id="x" inject_from="code"> -->"#.to_string(),
+                "  ",
+                "<!-- </snip> -->".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn can_detect_inline_blocks() {
+        let content = r#"Install v<!--<snip id="version">-->1.2.0<!--</snip>--> now
+<!-- <snip id="empty"> --><!-- </snip> -->
+"#;
+        let document = Document::parse(content, &Syntax::default()).unwrap();
+
+        let inline = &document.blocks[0];
+        assert!(document.is_inline(inline));
+        assert_eq!(document.content_lines(inline), vec!["1.2.0"]);
+
+        let empty = &document.blocks[1];
+        assert!(!document.is_inline(empty));
+        assert!(document.content_lines(empty).is_empty());
+    }
+
+    #[test]
+    fn can_skip_escaped_and_example_tags() {
+        let content = r#"Use `<snip id="inline-code">` to start a snippet.
+
+```md
+<!-- <snip id="fenced"> -->
+```
+
+<!-- <snip-raw> -->
+<!-- <snip id="raw"> -->
+<!-- </snip-raw> -->
+
+// \<snip id="escaped">
+<!-- <snip id="live"> -->
+<!-- </snip> -->
+"#;
+        let syntax = Syntax {
+            markdown: true,
+            fenced_examples: true,
+            ..Syntax::default()
+        };
+        let document = Document::parse(content, &syntax).unwrap();
+
+        assert_eq!(
+            document
+                .blocks
+                .iter()
+                .map(|block| block.open.id())
+                .collect::<Vec<_>>(),
+            vec![Some("live")]
+        );
+    }
+
+    #[test]
+    fn can_parse_tags_in_fenced_code_by_default() {
+        let content = r#"Use `<snip id="inline-code">` to start a snippet.
+
+```rust
+<!-- <snip id="fenced" inject_from="code"> -->
+<!-- </snip> -->
+```
+"#;
+        let syntax = Syntax {
+            markdown: true,
+            ..Syntax::default()
+        };
+        let document = Document::parse(content, &syntax).unwrap();
+
+        assert_eq!(
+            document
+                .blocks
+                .iter()
+                .map(|block| block.open.id())
+                .collect::<Vec<_>>(),
+            vec![Some("fenced")]
+        );
+    }
+
+    #[test]
+    fn fail_on_unclosed_raw_region() {
+        let content = "<!-- <snip-raw> -->\n<!-- <snip id=\"raw\"> -->\n";
+
+        let Err(ParseError::Diagnostic(diagnostic)) = Document::parse(content, &Syntax::default())
+        else {
+            panic!("expected a diagnostic");
+        };
+        assert_eq!(diagnostic.message, "raw region is never closed");
+    }
+
+    #[test]
+    fn can_parse_custom_keyword() {
+        let content = r#"<!-- <snip id="other-framework"> -->
+<!-- <doc-snippet id="a"> -->
+<!-- </doc-snippet> -->
+<!-- <doc-snippet id="b"/> -->
+"#;
+        let syntax = Syntax {
+            keyword: "doc-snippet".to_string(),
+            ..Syntax::default()
+        };
+        let document = Document::parse(content, &syntax).unwrap();
+
+        assert_eq!(
+            document
+                .blocks
+                .iter()
+                .map(|block| block.open.id())
+                .collect::<Vec<_>>(),
+            vec![Some("a"), Some("b")]
+        );
+        assert_eq!(
+            document.expand_placeholder(&document.blocks[1]).2,
+            "<!-- </doc-snippet> -->"
+        );
+    }
+
+    #[test]
+    fn can_find_hidden_lines() {
+        let content = r#"// <snip id="hidden">
+fn main() {
+    let config = setup(); // snip:hide
+    // snip:hide-start
+    assert!(config.valid);
+    // snip:hide-end
+    let data = load(&config).unwrap(); // snip:...
+    <!-- snip:... -->
+    // see snip:hidden and not-snip:hide
+    run(data);
+}
+// </snip>
+"#;
+        let document = Document::parse(content, &Syntax::default()).unwrap();
+
+        assert_eq!(
+            document.hidden_lines(&document.blocks[0]).unwrap(),
+            vec![
+                HiddenLines {
+                    start: 1,
+                    end: 1,
+                    ellipsis: None
+                },
+                HiddenLines {
+                    start: 2,
+                    end: 4,
+                    ellipsis: None
+                },
+                HiddenLines {
+                    start: 5,
+                    end: 5,
+                    ellipsis: Some("    // ...".to_string())
+                },
+                HiddenLines {
+                    start: 6,
+                    end: 6,
+                    ellipsis: Some("    <!-- ... -->".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn fail_on_unbalanced_hidden_region() {
+        let hidden_lines = |content: &str| {
+            let document = Document::parse(content, &Syntax::default()).unwrap();
+            match document.hidden_lines(&document.blocks[0]) {
+                Err(ParseError::Diagnostic(diagnostic)) => diagnostic,
+                _ => panic!("expected a diagnostic"),
+            }
+        };
+
+        let diagnostic = hidden_lines("// <snip id=\"x\">\n// snip:hide-start\nline\n// </snip>\n");
+        assert_eq!(diagnostic.message, "`snip:hide-start` is never closed");
+        assert_eq!(diagnostic.start, Some(Position { line: 2, column: 4 }));
+
+        let diagnostic = hidden_lines("// <snip id=\"x\">\nline\n// snip:hide-end\n// </snip>\n");
+        assert_eq!(
+            diagnostic.message,
+            "`snip:hide-end` has no matching `snip:hide-start`"
+        );
+
+        let diagnostic = hidden_lines(
+            "// <snip id=\"x\">\n// snip:hide-start\n// snip:hide-start\n// </snip>\n",
+        );
+        assert_eq!(
+            diagnostic.message,
+            "`snip:hide-start` inside a hidden region"
+        );
+        assert_eq!(
+            diagnostic.hint.as_deref(),
+            Some("close the region opened at line 2 first")
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Escapes the injected snippet content for the place it is injected in, e.g.
+//! an HTML page or a JSON string.
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// How the snippet content is escaped before the template is applied.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Escape {
+    /// Replaces `&`, `<`, `>`, `"` and `'` with HTML entities.
+    Html,
+    /// Escapes the content of a JSON string, without the quotes.
+    Json,
+    /// Escapes `\` and `"` for a Rust string literal, without the quotes.
+    RustStr,
+    /// Escapes `|` and replaces line breaks with `<br>` for a markdown table
+    /// cell.
+    MarkdownTable,
+    /// Replaces `&`, `<`, `>`, `"` and `'` with XML entities.
+    Xml,
+    /// Quotes the content as a single shell word.
+    Shell,
+}
+
+/// The values of the `escape` attribute.
+pub const ESCAPES: &[&str] = &["html", "json", "rust-str", "markdown-table", "xml", "shell"];
+
+impl FromStr for Escape {
+    type Err = ();
+
+    fn from_str(input: &str) -> std::result::Result<Self, ()> {
+        match input {
+            "html" => Ok(Self::Html),
+            "json" => Ok(Self::Json),
+            "rust-str" => Ok(Self::RustStr),
+            "markdown-table" => Ok(Self::MarkdownTable),
+            "xml" => Ok(Self::Xml),
+            "shell" => Ok(Self::Shell),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Escape {
+    #[must_use]
+    pub fn apply(self, content: &str) -> String {
+        match self {
+            Self::Html => escape_chars(content, |c| match c {
+                '&' => Some("&amp;".to_string()),
+                '<' => Some("&lt;".to_string()),
+                '>' => Some("&gt;".to_string()),
+                '"' => Some("&quot;".to_string()),
+                '\'' => Some("&#39;".to_string()),
+                _ => None,
+            }),
+            Self::Xml => escape_chars(content, |c| match c {
+                '&' => Some("&amp;".to_string()),
+                '<' => Some("&lt;".to_string()),
+                '>' => Some("&gt;".to_string()),
+                '"' => Some("&quot;".to_string()),
+                '\'' => Some("&apos;".to_string()),
+                _ => None,
+            }),
+            Self::Json => escape_chars(content, |c| match c {
+                '"' => Some("\\\"".to_string()),
+                '\\' => Some("\\\\".to_string()),
+                '\n' => Some("\\n".to_string()),
+                '\r' => Some("\\r".to_string()),
+                '\t' => Some("\\t".to_string()),
+                c if c.is_control() => Some(format!("\\u{:04x}", u32::from(c))),
+                _ => None,
+            }),
+            Self::RustStr => escape_chars(content, |c| match c {
+                '"' => Some("\\\"".to_string()),
+                '\\' => Some("\\\\".to_string()),
+                _ => None,
+            }),
+            Self::MarkdownTable => content
+                .lines()
+                .map(|line| line.replace('|', "\\|"))
+                .collect::<Vec<_>>()
+                .join("<br>"),
+            Self::Shell => format!("'{}'", content.replace('\'', "'\\''")),
+        }
+    }
+}
+
+fn escape_chars(content: &str, escape: impl Fn(char) -> Option<String>) -> String {
+    content.chars().fold(String::new(), |mut escaped, c| {
+        match escape(c) {
+            Some(replacement) => escaped.push_str(&replacement),
+            None => escaped.push(c),
+        }
+        escaped
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_escape() {
+        let content = "if a < b && c > \"d\" || e == 'f' {\n\tprintln!(\"\\n\");\n}";
+
+        assert_eq!(
+            Escape::Html.apply(content),
+            "if a &lt; b &amp;&amp; c &gt; &quot;d&quot; || e == &#39;f&#39; {\n\tprintln!(&quot;\\n&quot;);\n}"
+        );
+        assert_eq!(
+            Escape::Xml.apply(content),
+            "if a &lt; b &amp;&amp; c &gt; &quot;d&quot; || e == &apos;f&apos; {\n\tprintln!(&quot;\\n&quot;);\n}"
+        );
+        assert_eq!(
+            Escape::Json.apply(content),
+            r#"if a < b && c > \"d\" || e == 'f' {\n\tprintln!(\"\\n\");\n}"#
+        );
+        assert_eq!(
+            Escape::RustStr.apply(content),
+            "if a < b && c > \\\"d\\\" || e == 'f' {\n\tprintln!(\\\"\\\\n\\\");\n}"
+        );
+        assert_eq!(
+            Escape::MarkdownTable.apply(content),
+            "if a < b && c > \"d\" \\|\\| e == 'f' {<br>\tprintln!(\"\\n\");<br>}"
+        );
+        assert_eq!(
+            Escape::Shell.apply("echo 'hi' $HOME"),
+            r"'echo '\''hi'\'' $HOME'"
+        );
+        assert_eq!(Escape::Json.apply("\u{1}"), "\\u0001");
+    }
+
+    #[test]
+    fn can_parse_all_escapes() {
+        for escape in ESCAPES {
+            assert!(Escape::from_str(escape).is_ok(), "{escape}");
+        }
+        assert!(Escape::from_str("none").is_err());
+    }
+}
@@ -5,33 +5,37 @@ use std::{
     str::FromStr,
 };
 
-use lazy_static::lazy_static;
-use pest::{iterators::Pairs, Parser};
 use rayon::prelude::*;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use super::{
+    document::{Block, Document},
+    escape::{Escape, ESCAPES},
+    syntax::{Syntax, SyntaxResolver},
+    tag::{Tag, KNOWN_ATTRIBUTES},
+    template::{self, Engine, TemplateContext, TemplateError},
+    transform::Pipeline,
+};
 use crate::{
-    config::InjectConfig,
-    db::DBData,
-    errors::ParserResult,
-    parser::{html_tag, Rule, SnippetKind, SnippetParse, SnippetTemplate},
+    config::{Config, InjectConfig},
+    db::{DBData, Definition, Resolved, Scope},
+    errors::{Diagnostic, ParserResult},
+    parser::{NestedMode, SnippetKind, SnippetTemplate},
     read_file::RFile,
     walk::Walk,
     LINE_ENDING,
 };
 
-lazy_static! {
-    static ref RE_NORMALIZE_TEXT: Regex = Regex::new(r"[\s\r\n]+").unwrap();
-    static ref RE_SNIPPET_TEMPLATE_PLACEHOLDER: Regex =
-        Regex::new(r"(?m)(^\s*|)\{\s*snippet\}").unwrap();
-}
-
 const INJECT_ACTION: &str = "action";
 const INJECT_FROM_ATTRIBUTE_NAME: &str = "inject_from";
 const STRIP_PREFIX_ATTRIBUTE_NAME: &str = "strip_prefix";
 const ADD_PREFIX_ATTRIBUTE_NAME: &str = "add_prefix";
 const ADD_TEMPLATE: &str = "template";
+const NESTED_ATTRIBUTE_NAME: &str = "nested";
+const PREFER_ATTRIBUTE_NAME: &str = "prefer";
+const NAMESPACE_ATTRIBUTE_NAME: &str = "namespace";
+const TRANSFORM_ATTRIBUTE_NAME: &str = "transform";
+const ESCAPE_ATTRIBUTE_NAME: &str = "escape";
 
 /// A struct representing the injection summary result.
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -43,20 +47,33 @@ pub struct InjectSummary {
     pub actions: Vec<InjectStatus>,
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Default, PartialEq, Eq)]
 pub enum InjectAction {
+    #[default]
     Copy,
     #[cfg(feature = "exec")]
     Exec,
 }
 
+#[derive(Default)]
 pub struct InjectContentAction {
     pub snippet_id: String,
     pub kind: InjectAction,
     pub inject_from: SnippetKind,
+    /// The source to take the snippet from when the id is defined in several
+    /// sources.
+    pub prefer: Option<SnippetKind>,
     pub strip_prefix: Option<String>,
     pub add_prefix: Option<String>,
     pub template: Template,
+    pub nested: NestedMode,
+    /// The transforms applied to the snippet content before the template.
+    pub transform: Pipeline,
+    /// How the snippet content is escaped before the template.
+    pub escape: Option<Escape>,
+    /// The attributes snipdoc doesn't know, passed to the template as
+    /// variables.
+    pub params: BTreeMap<String, String>,
 }
 
 #[derive(Default)]
@@ -98,43 +115,47 @@ impl Template {
         }
     }
 
-    #[must_use]
+    /// Renders the template around the snippet content, see
+    /// [`template::render`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the template can't be rendered.
     pub fn before_inject(
         &self,
         content: &str,
+        context: &TemplateContext,
         custom_templates: &BTreeMap<String, SnippetTemplate>,
-    ) -> String {
+    ) -> Result<String, TemplateError> {
+        let mut engine = Engine::Legacy;
         let template = match self {
-            Self::Default => content.to_string(),
-            Self::Text => r"```text\n{snippet}\n```".to_string(),
-            Self::Json => r"```json\n{snippet}\n```".to_string(),
-            Self::Yaml => r"```yaml\n{snippet}\n```".to_string(),
-            Self::Toml => r"```toml\n{snippet}\n```".to_string(),
-            Self::Html => r"```html\n{snippet}\n```".to_string(),
-            Self::Rust => r"```rust\n{snippet}\n```".to_string(),
-            Self::Python => r"```python\n{snippet}\n```".to_string(),
-            Self::Go => r"```go\n{snippet}\n```".to_string(),
-            Self::Sql => r"```sql\n{snippet}\n```".to_string(),
-            Self::Shell => r"```shell\n{snippet}\n```".to_string(),
-            Self::Bash => r"```bash\n{snippet}\n```".to_string(),
-            Self::Sh => r"```sh\n{snippet}\n```".to_string(),
-            Self::Custom(template) => custom_templates.get(template).map_or_else(
-                || template.clone(),
-                |custom_template| custom_template.content.clone(),
-            ),
+            Self::Default => return Ok(content.to_string()),
+            Self::Text => r"```text\n{snippet}\n```",
+            Self::Json => r"```json\n{snippet}\n```",
+            Self::Yaml => r"```yaml\n{snippet}\n```",
+            Self::Toml => r"```toml\n{snippet}\n```",
+            Self::Html => r"```html\n{snippet}\n```",
+            Self::Rust => r"```rust\n{snippet}\n```",
+            Self::Python => r"```python\n{snippet}\n```",
+            Self::Go => r"```go\n{snippet}\n```",
+            Self::Sql => r"```sql\n{snippet}\n```",
+            Self::Shell => r"```shell\n{snippet}\n```",
+            Self::Bash => r"```bash\n{snippet}\n```",
+            Self::Sh => r"```sh\n{snippet}\n```",
+            Self::Custom(template) => match custom_templates.get(template) {
+                Some(custom_template) => {
+                    engine = custom_template.engine;
+                    custom_template.content.as_str()
+                }
+                None => template.as_str(),
+            },
         };
 
-        RE_SNIPPET_TEMPLATE_PLACEHOLDER
-            .replace_all(&template, |caps: &regex::Captures<'_>| {
-                let indent = caps.get(1).map_or("", |m| m.as_str());
-                let snippet_lines = content
-                    .lines()
-                    .map(|line| format!("{indent}{line}"))
-                    .collect::<Vec<_>>()
-                    .join(LINE_ENDING);
-                snippet_lines
-            })
-            .replace("\\n", LINE_ENDING)
+        let name = match self {
+            Self::Custom(name) => name.as_str(),
+            _ => "builtin",
+        };
+        template::render(name, template, engine, content, context)
     }
 
     #[must_use]
@@ -148,43 +169,94 @@ impl Template {
 }
 
 impl InjectContentAction {
-    pub fn new(attributes: &BTreeMap<String, String>) -> Option<Self> {
-        let snippet_id = attributes.get("id").map_or_else(
-            || {
-                tracing::debug!(
-                    attributes = format!("{:?}", attributes),
-                    "attribute id not found in the given attributes"
-                );
-                None
-            },
-            Some,
-        )?;
-
-        let inject_from = attributes.get(INJECT_FROM_ATTRIBUTE_NAME).map_or_else(
-            || {
-                tracing::trace!(
-                    attributes = format!("{:?}", attributes),
-                    "skip injection snippet. `inject_from` not found"
-                );
-                None
-            },
-            Some,
-        )?;
+    /// Builds the inject actions of a placeholder tag.
+    ///
+    /// Returns `Ok(None)` when the tag is not a placeholder.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error pointing at the attribute when the `transform` or
+    /// `escape` attribute is invalid.
+    pub fn new<'e>(
+        tag: &Tag,
+        input: &str,
+        config: &InjectConfig,
+        syntax: &Syntax,
+    ) -> ParserResult<'e, Option<Self>> {
+        let attributes = tag.attributes_map();
+        let Some(snippet_id) = attributes.get("id") else {
+            tracing::debug!(
+                attributes = format!("{:?}", attributes),
+                "attribute id not found in the given attributes"
+            );
+            return Ok(None);
+        };
+
+        let Some(inject_from) = attributes.get(INJECT_FROM_ATTRIBUTE_NAME) else {
+            tracing::trace!(
+                attributes = format!("{:?}", attributes),
+                "skip injection snippet. `inject_from` not found"
+            );
+            return Ok(None);
+        };
 
         let Ok(inject_from) = SnippetKind::from_str(inject_from) else {
             tracing::debug!(inject_from, " unsupported inject_from value.");
-            return None;
+            return Ok(None);
         };
 
-        Some(Self {
+        let transform = match tag.get(TRANSFORM_ATTRIBUTE_NAME) {
+            Some(transform) => {
+                transform
+                    .value
+                    .parse()
+                    .map_err(|err: super::transform::TransformError| {
+                        Diagnostic::new(&err.message)
+                            .at(input, transform.span.clone())
+                            .with_hint(&err.hint)
+                    })?
+            }
+            None => Pipeline::default(),
+        };
+        let escape = match tag.get(ESCAPE_ATTRIBUTE_NAME) {
+            Some(escape) if escape.value == "none" => None,
+            Some(escape) => Some(Escape::from_str(&escape.value).map_err(|()| {
+                Diagnostic::new(&format!("unknown escape `{}`", escape.value))
+                    .at(input, escape.span.clone())
+                    .with_hint(&format!("expected one of: {}, none", ESCAPES.join(", ")))
+            })?),
+            None => syntax.escape,
+        };
+
+        Ok(Some(Self {
             snippet_id: snippet_id.to_string(),
             inject_from,
+            prefer: attributes.get(PREFER_ATTRIBUTE_NAME).and_then(|prefer| {
+                SnippetKind::from_str(prefer)
+                    .inspect_err(|()| tracing::debug!(prefer, "unsupported prefer value"))
+                    .ok()
+            }),
             strip_prefix: attributes.get(STRIP_PREFIX_ATTRIBUTE_NAME).cloned(),
             add_prefix: attributes.get(ADD_PREFIX_ATTRIBUTE_NAME).cloned(),
             template: attributes
                 .get(ADD_TEMPLATE)
                 .map(|s| Template::new(s))
                 .unwrap_or_default(),
+            nested: attributes
+                .get(NESTED_ATTRIBUTE_NAME)
+                .and_then(|nested| {
+                    NestedMode::from_str(nested)
+                        .inspect_err(|()| tracing::debug!(nested, "unsupported nested value"))
+                        .ok()
+                })
+                .unwrap_or(config.nested),
+            transform,
+            escape,
+            params: attributes
+                .iter()
+                .filter(|(name, _)| !KNOWN_ATTRIBUTES.contains(&name.as_str()))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
             kind: attributes
                 .get(INJECT_ACTION)
                 .and_then(|a| match a.as_str() {
@@ -194,7 +266,7 @@ impl InjectContentAction {
                     _ => None,
                 })
                 .unwrap_or(InjectAction::Copy),
-        })
+        }))
     }
 }
 
@@ -202,20 +274,48 @@ impl InjectContentAction {
 #[derive(Debug, Serialize, Deserialize)]
 pub enum InjectStatus {
     /// The snippet found and contains the same content
-    Equal { snippet_id: String },
+    Equal {
+        snippet_id: String,
+        source: SnippetKind,
+    },
     /// The snippet found and the content was injected
-    Injected { snippet_id: String, content: String },
+    Injected {
+        snippet_id: String,
+        content: String,
+        source: SnippetKind,
+    },
     /// When has injected the snippet but not found snippet
     NotFound {
         snippet_id: String,
         snippet_kind: SnippetKind,
+        /// Existing ids close to the missing id.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        suggestions: Vec<String>,
+    },
+    /// The snippet was injected, but it is referenced by an alias or it is
+    /// deprecated
+    Deprecated {
+        snippet_id: String,
+        resolved_id: String,
+        message: Option<String>,
+    },
+    /// The snippet exists, but not in the source given by `inject_from`
+    KindMismatch {
+        snippet_id: String,
+        expected: SnippetKind,
+        found: SnippetKind,
+        /// The file defining the snippet.
+        source_path: PathBuf,
     },
 }
 
 pub struct Injector<'a> {
+    /// The folder of the injected file, `./dir#id` references are relative
+    /// to it.
     pub base_folder: &'a Path,
     pub input: &'a str,
     pub config: &'a InjectConfig,
+    pub syntax: &'a Syntax,
     pub db_data: &'a DBData,
 }
 
@@ -225,6 +325,9 @@ pub struct Injector<'a> {
 pub struct InjectorResult {
     pub root_folder: PathBuf,
     pub results: InjectSnippets,
+    /// Snippet ids defined more than once, with all their definitions.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub duplicates: BTreeMap<String, Vec<Definition>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -239,7 +342,7 @@ pub enum InjectedContent {
     /// same it to injector
     None,
     /// When error is encountered
-    Error(String),
+    Error(Diagnostic),
 }
 
 #[derive(Default)]
@@ -247,9 +350,27 @@ pub struct InjectStats {
     pub equals: u64,
     pub injects: u64,
     pub inject_unique_files: HashSet<PathBuf>,
-    pub errors: BTreeMap<PathBuf, String>,
-    pub not_found: BTreeMap<PathBuf, HashSet<String>>,
+    pub errors: BTreeMap<PathBuf, Diagnostic>,
+    /// The missing snippet ids by file, with the existing ids close to them.
+    pub not_found: BTreeMap<PathBuf, BTreeMap<String, Vec<String>>>,
     pub not_found_count: u64,
+    /// Deprecated snippet references by file, described with their
+    /// replacement.
+    pub deprecated: BTreeMap<PathBuf, Vec<String>>,
+    pub deprecated_count: u64,
+    /// Placeholders whose `inject_from` doesn't match the snippet source, by
+    /// file.
+    pub kind_mismatches: BTreeMap<PathBuf, Vec<KindMismatch>>,
+    pub kind_mismatch_count: u64,
+}
+
+/// A placeholder whose `inject_from` doesn't match the source of the snippet.
+#[derive(Debug, Clone)]
+pub struct KindMismatch {
+    pub snippet_id: String,
+    pub expected: SnippetKind,
+    pub found: SnippetKind,
+    pub source_path: PathBuf,
 }
 
 impl InjectSnippets {
@@ -257,6 +378,21 @@ impl InjectSnippets {
         self.0.iter()
     }
 
+    /// Adds errors found while collecting the snippets. They replace the
+    /// injection result of the file unless it is already an error, so a
+    /// broken file is reported and never written.
+    pub fn add_errors(&mut self, errors: &BTreeMap<PathBuf, Diagnostic>) {
+        for (path, diagnostic) in errors {
+            let status = self
+                .0
+                .entry(path.clone())
+                .or_insert_with(|| InjectedContent::Error(diagnostic.clone()));
+            if !matches!(status, InjectedContent::Error(_)) {
+                *status = InjectedContent::Error(diagnostic.clone());
+            }
+        }
+    }
+
     #[must_use]
     pub fn stats(&self) -> InjectStats {
         let mut stats = InjectStats::default();
@@ -271,20 +407,56 @@ impl InjectSnippets {
                                 stats.injects += 1;
                                 stats.inject_unique_files.insert(file.clone());
                             }
-                            InjectStatus::NotFound { snippet_id, .. } => {
+                            InjectStatus::NotFound {
+                                snippet_id,
+                                suggestions,
+                                ..
+                            } => {
                                 stats
                                     .not_found
                                     .entry(file.clone())
-                                    .or_insert_with(|| HashSet::from([snippet_id.to_string()]))
-                                    .insert(snippet_id.to_string());
+                                    .or_default()
+                                    .insert(snippet_id.to_string(), suggestions.clone());
                                 stats.not_found_count += 1;
                             }
+                            InjectStatus::Deprecated {
+                                snippet_id,
+                                resolved_id,
+                                message,
+                            } => {
+                                let description = message.as_ref().map_or_else(
+                                    || format!("{snippet_id}, renamed to {resolved_id}"),
+                                    |message| format!("{snippet_id}, {message}"),
+                                );
+                                stats
+                                    .deprecated
+                                    .entry(file.clone())
+                                    .or_default()
+                                    .push(description);
+                                stats.deprecated_count += 1;
+                            }
+                            InjectStatus::KindMismatch {
+                                snippet_id,
+                                expected,
+                                found,
+                                source_path,
+                            } => {
+                                stats.kind_mismatches.entry(file.clone()).or_default().push(
+                                    KindMismatch {
+                                        snippet_id: snippet_id.clone(),
+                                        expected: expected.clone(),
+                                        found: found.clone(),
+                                        source_path: source_path.clone(),
+                                    },
+                                );
+                                stats.kind_mismatch_count += 1;
+                            }
                         }
                     }
                 }
                 InjectedContent::None => (),
                 InjectedContent::Error(err) => {
-                    stats.errors.insert(file.clone(), err.to_string());
+                    stats.errors.insert(file.clone(), err.clone());
                 }
             }
         }
@@ -300,12 +472,14 @@ impl<'a> Injector<'a> {
         base_folder: &'a Path,
         input: &'a str,
         config: &'a InjectConfig,
+        syntax: &'a Syntax,
         db_data: &'a DBData,
     ) -> Self {
         Self {
             base_folder,
             input,
             config,
+            syntax,
             db_data,
         }
     }
@@ -313,7 +487,7 @@ impl<'a> Injector<'a> {
     /// Constructs a `Collector` instance by collecting snippets from files
     /// within the provided `Walk`.
     #[must_use]
-    pub fn walk(walk: &Walk, db_data: &DBData, config: &InjectConfig) -> InjectorResult {
+    pub fn walk(walk: &Walk, db_data: &DBData, config: &Config) -> InjectorResult {
         let files = walk.get_files();
         tracing::debug!(
             count_files = files.len(),
@@ -321,24 +495,43 @@ impl<'a> Injector<'a> {
             "start inject snippets"
         );
 
+        let resolver = SyntaxResolver::new(config);
         let results = files
             .par_iter()
-            .filter_map(|path| match RFile::new(path) {
+            .map(|path| match RFile::new(path) {
                 Ok(r_file) => {
                     let span = tracing::info_span!("inject", path = %path.display());
                     let _guard = span.enter();
 
-                    let status =
-                        Self::inject(walk.folder.as_path(), &r_file.content, config, db_data);
-                    Some((path.clone(), status))
+                    let syntax = resolver.resolve(path.strip_prefix(&walk.folder).unwrap_or(path));
+                    let status = match Self::inject(
+                        path.parent().unwrap_or(walk.folder.as_path()),
+                        &r_file.content,
+                        &config.inject,
+                        &syntax,
+                        db_data,
+                    ) {
+                        InjectedContent::Error(diagnostic) => {
+                            InjectedContent::Error(diagnostic.with_path(path))
+                        }
+                        status => status,
+                    };
+                    (path.clone(), status)
+                }
+                Err(err) => {
+                    tracing::debug!(path = %path.display(), err = %err, "could not read the file");
+                    (
+                        path.clone(),
+                        InjectedContent::Error(Diagnostic::new(&err.to_string()).with_path(path)),
+                    )
                 }
-                Err(_err) => None,
             })
             .collect::<BTreeMap<PathBuf, InjectedContent>>();
 
         InjectorResult {
             root_folder: walk.folder.clone(),
             results: InjectSnippets(results),
+            duplicates: db_data.duplicates(),
         }
     }
 
@@ -352,9 +545,10 @@ impl<'a> Injector<'a> {
         base_folder: &Path,
         input: &str,
         config: &InjectConfig,
+        syntax: &Syntax,
         db_data: &DBData,
     ) -> InjectedContent {
-        match Injector::new(base_folder, input, config, db_data).run() {
+        match Injector::new(base_folder, input, config, syntax, db_data).run() {
             Ok(summary) => {
                 if summary.actions.is_empty() {
                     tracing::debug!("not found inject content");
@@ -365,7 +559,7 @@ impl<'a> Injector<'a> {
             }
             Err(err) => {
                 tracing::debug!(err = %err, "could not pars the file. invalid schema");
-                InjectedContent::Error(err.to_string())
+                InjectedContent::Error(Diagnostic::from(&err))
             }
         }
     }
@@ -378,10 +572,10 @@ impl<'a> Injector<'a> {
     /// This function may return an error if it fails to parse the input file.
     /// Other errors encountered during parsing will be logged.
     pub fn run(&self) -> ParserResult<'_, InjectSummary> {
-        let pairs = SnippetParse::parse(Rule::file, self.input)?;
+        let document = Document::parse(self.input, self.syntax)?;
 
         let mut inject_summary = InjectSummary::default();
-        self.inject_snippets(pairs, &mut inject_summary)?;
+        self.inject_snippets(&document, &mut inject_summary)?;
 
         Ok(inject_summary)
     }
@@ -389,124 +583,207 @@ impl<'a> Injector<'a> {
     /// Injects snippets in the input file content based on the provided
     /// `snippets` map.
     ///
-    /// # Errors
+    /// Only top level blocks are replaced, the content of a block which is
+    /// not a placeholder is kept as is, including its inner blocks. A block
+    /// overlapping a previous block is kept as is. A self-closing placeholder
+    /// is expanded into a full block. An inline block gets the snippet
+    /// content without line breaks around it.
     ///
-    /// This function may return an error if it fails to parse the input file.
-    /// Other errors encountered during parsing will be logged.
-    ///
-    /// # Panics
+    /// # Errors
     ///
-    /// This function assumes that the parsing configuration always captures a
-    /// snippet containing a tag open. If this assumption is violated, it
-    /// indicates a misconfiguration or a critical issue in the parser's
-    /// behavior. Consequently, in production code, encountering this panic
-    /// indicates a severe problem that requires immediate attention.
-    /// In testing scenarios, this panic should be captured to ensure the
-    /// correctness of the parser.
-    #[allow(clippy::only_used_in_recursion)]
+    /// This function may return an error if it fails to write the summary
+    /// content.
     fn inject_snippets(
         &self,
-        pairs: Pairs<'a, Rule>,
-        summary: &'a mut InjectSummary,
+        document: &Document<'_>,
+        summary: &mut InjectSummary,
     ) -> ParserResult<'a, ()> {
-        if pairs.len() == 0 {
-            return Ok(());
-        }
-
-        for pair in pairs {
-            let inner = pair.clone().into_inner();
-
-            if pair.as_rule() == Rule::snippet {
-                let children: Pairs<'_, Rule> = pair.clone().into_inner();
+        let mut pos = 0;
 
-                let tag_open = html_tag::get_tag_open(&children);
-                let tag_close = html_tag::get_tag_close(children.clone());
-
-                let attributes = match html_tag::get_tag_attributes(tag_open) {
-                    Ok(attributes) => attributes,
-                    Err(err) => {
-                        tracing::debug!(tag_open, err = %err, "could not extract attributes from the tag");
-                        continue;
-                    }
-                };
-
-                let inject_content_actions = InjectContentAction::new(&attributes);
-
-                if let Some(inject_actions) = inject_content_actions {
-                    if let Some(snippet) = self.db_data.snippets.get(&inject_actions.snippet_id) {
-                        if inject_actions.inject_from == SnippetKind::Any
-                            || inject_actions.inject_from == snippet.kind
-                        {
-                            let snippet_content =
-                                snippet.create_content(&inject_actions, &self.db_data.templates);
+        for block in document.blocks.iter().filter(|block| block.depth == 0) {
+            // A block overlapping the previous one was already written with it.
+            if block.span().start < pos {
+                tracing::debug!(
+                    tag_open = document.text(block.open.tag.span.clone()),
+                    "skip block overlapping the previous block"
+                );
+                continue;
+            }
 
-                            let comment_tag = html_tag::get_comment_tag_open(&children);
-                            let close_tag_of_tag_open =
-                                html_tag::get_comment_tag_of_tag_open(&children);
+            summary
+                .content
+                .write_str(document.text(pos..block.span().start))?;
+            pos = block.span().end;
 
-                            let inject_result = format!(
-                                "{comment_tag}{tag_open}{}{snippet_content}{LINE_ENDING}{tag_close}",
-                                close_tag_of_tag_open.unwrap_or_default()
-                            );
+            let block_content = document.text(block.span());
+            let attributes = block.open.tag.attributes_map();
 
-                            summary.content.write_str(&inject_result)?;
+            let inject_content_actions = InjectContentAction::new(
+                &block.open.tag,
+                document.input,
+                self.config,
+                self.syntax,
+            )?;
 
-                            if Self::is_str_equal(pair.as_str(), &inject_result) {
-                                tracing::debug!(
-                                    snippet_id = inject_actions.snippet_id,
-                                    "equal snippet value"
-                                );
-                                summary.actions.push(InjectStatus::Equal {
-                                    snippet_id: inject_actions.snippet_id.to_string(),
-                                });
-                            } else {
-                                tracing::debug!(
-                                    snippet_id = inject_actions.snippet_id,
-                                    "snippet content replaced"
-                                );
-                                summary.actions.push(InjectStatus::Injected {
-                                    snippet_id: inject_actions.snippet_id.to_string(),
-                                    content: snippet_content,
-                                });
-                            }
-                        } else {
-                            tracing::debug!(
-                                snippet_id = inject_actions.snippet_id,
-                                kind = %snippet.kind,
-                                "not found snipper to inject with same inject_from value"
-                            );
-                            // summary.actions.push(InjectStatus::NotFound {
-                            //     snippet_id: inject_actions.snippet_id.to_string(),
-                            //     snippet_kind: inject_actions.inject_from,
-                            // });
-                            summary.content.write_str(pair.as_str())?;
-                        }
+            if let Some(inject_actions) = inject_content_actions {
+                let scope = Scope {
+                    namespace: attributes
+                        .get(NAMESPACE_ATTRIBUTE_NAME)
+                        .or(self.syntax.namespace.as_ref())
+                        .map(String::as_str),
+                    root: self.base_folder,
+                };
+                if let Some(found) =
+                    self.db_data
+                        .get(&inject_actions.snippet_id, &scope, &SnippetKind::Any, None)
+                {
+                    if let Some(resolved) = self.db_data.get(
+                        &inject_actions.snippet_id,
+                        &scope,
+                        &inject_actions.inject_from,
+                        inject_actions.prefer.as_ref(),
+                    ) {
+                        self.inject_snippet(document, block, &inject_actions, resolved, summary)?;
                     } else {
                         tracing::debug!(
                             snippet_id = inject_actions.snippet_id,
-                            "not found snipper to inject"
+                            kind = %found.snippet.kind,
+                            "not found snipper to inject with same inject_from value"
                         );
-                        summary.actions.push(InjectStatus::NotFound {
+                        summary.actions.push(InjectStatus::KindMismatch {
                             snippet_id: inject_actions.snippet_id.to_string(),
-                            snippet_kind: inject_actions.inject_from,
+                            expected: inject_actions.inject_from,
+                            found: found.snippet.kind.clone(),
+                            source_path: found.snippet.path.clone(),
                         });
-                        summary.content.write_str(pair.as_str())?;
+                        summary.content.write_str(block_content)?;
                     }
                 } else {
-                    summary.content.write_str(pair.as_str())?;
+                    tracing::debug!(
+                        snippet_id = inject_actions.snippet_id,
+                        "not found snipper to inject"
+                    );
+                    summary.actions.push(InjectStatus::NotFound {
+                        snippet_id: inject_actions.snippet_id.to_string(),
+                        snippet_kind: inject_actions.inject_from,
+                        suggestions: self.db_data.suggestions(&inject_actions.snippet_id),
+                    });
+                    summary.content.write_str(block_content)?;
                 }
             } else {
-                self.inject_snippets(inner.clone(), summary)?;
-                if inner.len() == 0 {
-                    summary.content.write_str(pair.as_str())?;
-                }
+                summary.content.write_str(block_content)?;
             }
         }
+
+        summary
+            .content
+            .write_str(document.text(pos..document.input.len()))?;
         Ok(())
     }
 
-    fn is_str_equal(a: &str, b: &str) -> bool {
-        RE_NORMALIZE_TEXT.replace_all(a, "") == RE_NORMALIZE_TEXT.replace_all(b, "")
+    /// Writes the given block with the resolved snippet injected in it and
+    /// reports whether the block changed and whether the snippet is referenced
+    /// through a deprecated id.
+    ///
+    /// # Errors
+    ///
+    /// This function may return an error if the snippet content can not be
+    /// rendered or written to the summary content.
+    fn inject_snippet(
+        &self,
+        document: &Document<'_>,
+        block: &Block,
+        inject_actions: &InjectContentAction,
+        Resolved { snippet, alias }: Resolved<'_>,
+        summary: &mut InjectSummary,
+    ) -> ParserResult<'a, ()> {
+        let block_content = document.text(block.span());
+        let snippet_content = snippet
+            .create_content(inject_actions, &self.db_data.templates)
+            .map_err(|err| {
+                let tag = block.open.tag.get(ADD_TEMPLATE).map_or_else(
+                    || block.open.tag.span.clone(),
+                    |template| template.span.clone(),
+                );
+                let diagnostic = Diagnostic::new(&err.message).at(document.input, tag);
+                match &err.hint {
+                    Some(hint) => diagnostic.with_hint(hint),
+                    None => diagnostic,
+                }
+            })?;
+
+        let (open_text, close_indent, close_text) = if block.is_placeholder() {
+            document.expand_placeholder(block)
+        } else {
+            (
+                document.text(block.open.span.clone()).to_string(),
+                document.close_indent(block),
+                document.text(block.close.span.clone()).to_string(),
+            )
+        };
+        let inline = document.is_inline(block);
+        // A line break would turn the inline block into a line based block on
+        // the next run.
+        if inline && snippet_content.contains('\n') {
+            return Err(
+                Diagnostic::new("a snippet with several lines can't be injected inline")
+                    .at(document.input, block.open.tag.span.clone())
+                    .with_hint("move the tags to their own lines")
+                    .into(),
+            );
+        }
+        let inject_result = if inline {
+            format!("{open_text}{snippet_content}{close_text}")
+        } else {
+            format!(
+                "{open_text}{LINE_ENDING}{snippet_content}{LINE_ENDING}{close_indent}{close_text}"
+            )
+        };
+
+        summary.content.write_str(&inject_result)?;
+
+        // Inline snippets are part of a sentence, any whitespace
+        // change matters.
+        let is_equal = if inline {
+            block_content == inject_result
+        } else {
+            self.config.compare.is_equal(block_content, &inject_result)
+        };
+
+        if is_equal {
+            tracing::debug!(
+                snippet_id = inject_actions.snippet_id,
+                "equal snippet value"
+            );
+            summary.actions.push(InjectStatus::Equal {
+                snippet_id: inject_actions.snippet_id.to_string(),
+                source: snippet.kind.clone(),
+            });
+        } else {
+            tracing::debug!(
+                snippet_id = inject_actions.snippet_id,
+                "snippet content replaced"
+            );
+            summary.actions.push(InjectStatus::Injected {
+                snippet_id: inject_actions.snippet_id.to_string(),
+                content: snippet_content,
+                source: snippet.kind.clone(),
+            });
+        }
+
+        if alias.is_some() || snippet.deprecated.is_some() {
+            tracing::debug!(
+                snippet_id = inject_actions.snippet_id,
+                resolved_id = snippet.id,
+                "deprecated snippet reference"
+            );
+            summary.actions.push(InjectStatus::Deprecated {
+                snippet_id: inject_actions.snippet_id.to_string(),
+                resolved_id: snippet.id.clone(),
+                message: snippet.deprecated.clone(),
+            });
+        }
+        Ok(())
     }
 }
 
@@ -516,7 +793,33 @@ mod tests {
     use insta::{assert_debug_snapshot, with_settings};
 
     use super::*;
-    use crate::{parser::Snippet, tests_cfg};
+    use crate::{
+        errors::ParseError,
+        parser::{LineRange, Snippet},
+        tests_cfg,
+    };
+
+    fn inject(
+        content: &str,
+        syntax: &Syntax,
+        db_data: &DBData,
+    ) -> ParserResult<'static, InjectSummary> {
+        Injector::new(
+            Path::new("."),
+            content,
+            &InjectConfig::default(),
+            syntax,
+            db_data,
+        )
+        .run()
+    }
+
+    fn db_data(snippets: BTreeMap<String, Snippet>) -> DBData {
+        DBData {
+            snippets,
+            ..DBData::default()
+        }
+    }
 
     #[test]
     fn get_inject() {
@@ -560,22 +863,205 @@ not-found
 
 "#;
 
-        let snippets: BTreeMap<String, Snippet> = tests_cfg::get_snippet_to_inject();
-        let inject_config = InjectConfig::default();
-        let base_inject_path = PathBuf::from(".");
-        let db_data = DBData {
-            snippets,
-            templates: BTreeMap::new(),
+        let db_data = db_data(tests_cfg::get_snippet_to_inject());
+
+        with_settings!({filters => tests_cfg::redact::all()}, {
+            assert_debug_snapshot!(inject(content, &Syntax::default(), &db_data));
+        });
+    }
+
+    #[test]
+    fn can_inject_into_block_comments() {
+        let content = r#"/* <snip id="description" inject_from="code"> */
+/* </snip> */
+
+    (*<snip id="description" inject_from="code">*)
+    (*</snip>*)
+"#;
+
+        let db_data = db_data(tests_cfg::get_snippet_to_inject());
+
+        with_settings!({filters => tests_cfg::redact::all()}, {
+            assert_debug_snapshot!(inject(content, &Syntax::default(), &db_data));
+        });
+    }
+
+    #[test]
+    fn can_expand_placeholders() {
+        let content = r#"# Snipdoc
+<!-- <snip id="description" inject_from="code"/> -->
+
+- item
+  // <snip id="description" inject_from="code" />
+
+<!-- <snip id="not-found" inject_from="code"/> -->
+"#;
+
+        let db_data = db_data(tests_cfg::get_snippet_to_inject());
+
+        with_settings!({filters => tests_cfg::redact::all()}, {
+            assert_debug_snapshot!(inject(content, &Syntax::default(), &db_data));
+        });
+    }
+
+    #[test]
+    fn can_inject_inline_snippets() {
+        let content = r#"Run <!--<snip id="description" inject_from="code">-->old<!--</snip>--> to start.
+Already <!--<snip id="description" inject_from="code">-->snipdoc<!--</snip>--> here.
+Spaces <!--<snip id="description" inject_from="code">--> snipdoc <!--</snip>--> matter.
+"#;
+
+        let db_data = db_data(tests_cfg::get_snippet_to_inject());
+
+        with_settings!({filters => tests_cfg::redact::all()}, {
+            assert_debug_snapshot!(inject(content, &Syntax::default(), &db_data));
+        });
+    }
+
+    #[test]
+    fn can_inject_inline_snippets_once() {
+        let content = r#"Run <!--<snip id="description" inject_from="code">-->old<!--</snip>--> to start.
+"#;
+        let db_data = db_data(tests_cfg::get_snippet_to_inject());
+
+        let injected = inject(content, &Syntax::default(), &db_data).unwrap();
+        assert!(matches!(
+            injected.actions.as_slice(),
+            [InjectStatus::Injected { .. }]
+        ));
+        let reinjected = inject(&injected.content, &Syntax::default(), &db_data).unwrap();
+        assert_eq!(reinjected.content, injected.content);
+        assert!(matches!(
+            reinjected.actions.as_slice(),
+            [InjectStatus::Equal { .. }]
+        ));
+    }
+
+    #[test]
+    fn fail_on_multi_line_inline_snippets() {
+        let content = r#"Run <!--<snip id="installation" inject_from="code">-->old<!--</snip>--> to start.
+"#;
+        let db_data = db_data(tests_cfg::get_snippet_to_inject());
+
+        let Err(ParseError::Diagnostic(diagnostic)) = inject(content, &Syntax::default(), &db_data)
+        else {
+            panic!("expected a diagnostic");
         };
-        let injector = Injector::new(
-            base_inject_path.as_path(),
-            content,
-            &inject_config,
-            &db_data,
+        assert_eq!(
+            diagnostic.message,
+            "a snippet with several lines can't be injected inline"
+        );
+        assert_eq!(
+            diagnostic.tag.as_deref(),
+            Some(r#"<snip id="installation" inject_from="code">"#)
+        );
+    }
+
+    #[test]
+    fn can_inject_bare_tags() {
+        let content = r#"Usage:
+<snip id="description" inject_from="code">
+old
+</snip>
+
+  <snip id="description" inject_from="code"/>
+"#;
+
+        let syntax = Syntax {
+            bare: true,
+            ..Syntax::default()
+        };
+        let db_data = db_data(tests_cfg::get_snippet_to_inject());
+
+        with_settings!({filters => tests_cfg::redact::all()}, {
+            assert_debug_snapshot!(inject(content, &syntax, &db_data));
+        });
+    }
+
+    #[test]
+    fn can_transform_snippets() {
+        let content = r#"<!-- <snip id="description" inject_from="code" transform="replace('s', 'S') | max_lines(1, '...')" template="sh"> -->
+<!-- </snip> -->
+"#;
+
+        let db_data = db_data(tests_cfg::get_snippet_to_inject());
+
+        with_settings!({filters => tests_cfg::redact::all()}, {
+            assert_debug_snapshot!(inject(content, &Syntax::default(), &db_data));
+        });
+
+        let content = r#"<!-- <snip id="description" inject_from="code" transform="dedent | lines(3-1)"> -->
+<!-- </snip> -->
+"#;
+        let err = inject(content, &Syntax::default(), &db_data).unwrap_err();
+
+        assert_eq!(err.to_string(), "1:48: invalid line range `3-1`");
+    }
+
+    #[test]
+    fn can_escape_snippets() {
+        let content = r#"<table><tr><td><!--<snip id="compare" inject_from="code">--><!--</snip>--></td></tr></table>
+<!-- <snip id="compare" inject_from="code" escape="markdown-table"/> -->
+<!-- <snip id="compare" inject_from="code" escape="none"/> -->
+"#;
+
+        let syntax = Syntax {
+            escape: Some(Escape::Html),
+            ..Syntax::default()
+        };
+        let mut snippet = tests_cfg::get_snippet();
+        snippet.content = "a < b || c > d".to_string();
+        let db_data = db_data(BTreeMap::from([("compare".to_string(), snippet)]));
+
+        with_settings!({filters => tests_cfg::redact::all()}, {
+            assert_debug_snapshot!(inject(content, &syntax, &db_data));
+        });
+
+        let content = r#"<!-- <snip id="compare" inject_from="code" escape="latex"/> -->"#;
+        let Err(ParseError::Diagnostic(diagnostic)) = inject(content, &syntax, &db_data) else {
+            panic!("expected a diagnostic");
+        };
+
+        assert_eq!(diagnostic.message, "unknown escape `latex`");
+        assert_eq!(
+            diagnostic.hint.as_deref(),
+            Some("expected one of: html, json, rust-str, markdown-table, xml, shell, none")
         );
+    }
+
+    #[test]
+    fn can_render_templates_with_params() {
+        let content = r#"<!-- <snip id="usage" inject_from="code" template="details" title="Install"> -->
+<!-- </snip> -->
+"#;
+
+        let mut snippet = tests_cfg::get_snippet();
+        snippet.lines = Some(LineRange { start: 3, end: 4 });
+        let db_data = DBData {
+            snippets: BTreeMap::from([("usage".to_string(), snippet)]),
+            templates: BTreeMap::from([(
+                "details".to_string(),
+                SnippetTemplate {
+                    content: "<details><summary>{{ title }}</summary>\n\n```{{ language }}\n{% for line in snippet_lines %}{{ line }}\n{% endfor %}```\n{{ path }}:{{ line_start }}-{{ line_end }}\n</details>".to_string(),
+                    engine: Engine::Jinja,
+                },
+            )]),
+            ..DBData::default()
+        };
 
         with_settings!({filters => tests_cfg::redact::all()}, {
-            assert_debug_snapshot!(injector.run());
+            assert_debug_snapshot!(inject(content, &Syntax::default(), &db_data));
         });
+
+        let content = r#"<!-- <snip id="usage" inject_from="code" template="details"/> -->"#;
+        let Err(ParseError::Diagnostic(diagnostic)) = inject(content, &Syntax::default(), &db_data)
+        else {
+            panic!("expected a diagnostic");
+        };
+
+        assert_eq!(
+            diagnostic.message,
+            "template `details` uses undefined variable `title`"
+        );
     }
 }
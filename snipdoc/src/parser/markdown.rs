@@ -0,0 +1,158 @@
+//! Finds the code in a markdown document, where snip tags are shown as
+//! examples and must not be parsed.
+use std::ops::Range;
+
+/// Returns the ranges of the inline code spans in the input, and of the
+/// fenced code blocks when `include_fences` is set, ordered by position. Code
+/// spans are never searched inside fenced code blocks.
+#[must_use]
+pub fn code_ranges(input: &str, include_fences: bool) -> Vec<Range<usize>> {
+    let fences = fenced_blocks(input);
+
+    let mut ranges = vec![];
+    let mut from = 0;
+    for fence in &fences {
+        ranges.extend(code_spans(input, from..fence.start));
+        if include_fences {
+            ranges.push(fence.clone());
+        }
+        from = fence.end;
+    }
+    ranges.extend(code_spans(input, from..input.len()));
+    ranges
+}
+
+/// Finds the fenced code blocks, from the opening fence line to the end of the
+/// closing fence line. A block that is never closed runs to the end of the
+/// input.
+fn fenced_blocks(input: &str) -> Vec<Range<usize>> {
+    let mut blocks = vec![];
+    // The fence character, the fence length and the block start.
+    let mut open: Option<(char, usize, usize)> = None;
+
+    let mut line_start = 0;
+    for line in input.split_inclusive('\n') {
+        let line_end = line_start + line.len();
+        let trimmed = line.trim();
+
+        match open {
+            None => {
+                if let Some((fence, len)) = fence(trimmed) {
+                    // A backtick fence can't have backticks in its info string.
+                    if fence == '`' && trimmed[len..].contains('`') {
+                        line_start = line_end;
+                        continue;
+                    }
+                    open = Some((fence, len, line_start));
+                }
+            }
+            Some((char, len, start)) => {
+                if fence(trimmed).is_some_and(|(c, l)| c == char && l >= len && l == trimmed.len())
+                {
+                    blocks.push(start..line_end);
+                    open = None;
+                }
+            }
+        }
+        line_start = line_end;
+    }
+
+    if let Some((_, _, start)) = open {
+        blocks.push(start..input.len());
+    }
+    blocks
+}
+
+/// Returns the character and the length of the fence starting the line.
+fn fence(line: &str) -> Option<(char, usize)> {
+    let char = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.len() - line.trim_start_matches(char).len();
+    (len >= 3).then_some((char, len))
+}
+
+/// Finds the inline code spans in the given range. A span is closed by a
+/// backtick run of the same length, before the end of the paragraph.
+fn code_spans(input: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let bytes = input.as_bytes();
+    let mut spans = vec![];
+
+    let mut i = range.start;
+    while i < range.end {
+        if bytes[i] != b'`' || (i > 0 && bytes[i - 1] == b'\\') {
+            i += 1;
+            continue;
+        }
+
+        let len = backtick_run(bytes, i, range.end);
+        let paragraph_end = input[i..range.end]
+            .find("\n\n")
+            .map_or(range.end, |end| i + end);
+
+        let mut j = i + len;
+        let mut close = None;
+        while j < paragraph_end {
+            if bytes[j] == b'`' {
+                let run = backtick_run(bytes, j, paragraph_end);
+                if run == len {
+                    close = Some(j + run);
+                    break;
+                }
+                j += run;
+            } else {
+                j += 1;
+            }
+        }
+
+        if let Some(end) = close {
+            spans.push(i..end);
+            i = end;
+        } else {
+            i += len;
+        }
+    }
+    spans
+}
+
+fn backtick_run(bytes: &[u8], from: usize, end: usize) -> usize {
+    bytes[from..end].iter().take_while(|b| **b == b'`').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_find_code_ranges() {
+        let input = r#"Use `<snip id="a">` or ``<snip id="`b`">``, not \`<snip>.
+```md
+<!-- <snip id="c"> -->
+````
+~~~
+inside
+~~~
+````text
+unclosed
+"#;
+        let ranges = |fences| {
+            code_ranges(input, fences)
+                .into_iter()
+                .map(|range| &input[range])
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ranges(true),
+            vec![
+                "`<snip id=\"a\">`",
+                "``<snip id=\"`b`\">``",
+                "```md\n<!-- <snip id=\"c\"> -->\n````\n",
+                "~~~\ninside\n~~~\n",
+                "````text\nunclosed\n",
+            ]
+        );
+        assert_eq!(
+            ranges(false),
+            vec!["`<snip id=\"a\">`", "``<snip id=\"`b`\">``"]
+        );
+    }
+}
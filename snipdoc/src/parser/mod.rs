@@ -1,10 +1,17 @@
 mod actions;
 pub mod collector;
-mod html_tag;
+mod document;
+pub mod escape;
 pub mod injector;
+mod markdown;
+pub mod renamer;
+pub mod syntax;
+mod tag;
+pub mod template;
+pub mod transform;
 
 use core::fmt;
-use std::{collections::BTreeMap, path::PathBuf, str::FromStr};
+use std::{collections::BTreeMap, ops::Range, path::PathBuf, str::FromStr};
 
 use pest_derive::Parser;
 use serde::{Deserialize, Serialize};
@@ -22,15 +29,137 @@ pub struct Snippet {
     pub content: String,
     pub kind: SnippetKind,
     pub path: PathBuf,
+    /// The lines of the content holding nested snippets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nested: Vec<NestedLines>,
+    /// The lines of the content hidden from the docs by hide markers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden: Vec<HiddenLines>,
+    /// The lines of the content in the snippet file, unknown for snippets
+    /// defined in yaml.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<LineRange>,
+    /// Former ids which still resolve to this snippet.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Set when the snippet should no longer be referenced, with a hint for
+    /// the replacement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+}
+
+/// The lines of a snippet holding a nested snippet, from the line of its open
+/// marker to the line of its close marker. A marker of an overlapping snippet
+/// has the same open and close line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NestedLines {
+    pub open: usize,
+    pub close: usize,
+    /// Where the markers of the nested snippet are in their line, so a marker
+    /// sharing its line with other text can be removed alone.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<LineSpan>,
+}
+
+/// A range of bytes in a line of a snippet.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineSpan {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A range of lines in a file, starting at 1 and inclusive.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The lines of a snippet hidden by `snip:hide` markers, from `start` to
+/// `end`. The lines are replaced by the `ellipsis` line when set, e.g. for
+/// `// snip:...`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HiddenLines {
+    pub start: usize,
+    pub end: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ellipsis: Option<String>,
+}
+
+/// How nested snippets are handled when injecting a snippet.
+#[derive(Default, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NestedMode {
+    /// Removes the nested markers and keeps their content, dropping the
+    /// lines left without text.
+    #[default]
+    Strip,
+    /// Keeps the nested markers as written.
+    Keep,
+    /// Drops the nested snippets along with their content.
+    Omit,
+}
+
+impl FromStr for NestedMode {
+    type Err = ();
+
+    fn from_str(input: &str) -> std::result::Result<Self, ()> {
+        match input {
+            "strip" => Ok(Self::Strip),
+            "keep" => Ok(Self::Keep),
+            "omit" => Ok(Self::Omit),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How an injected block is compared with the block in the file to decide
+/// whether it is up to date.
+#[derive(Default, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompareMode {
+    /// The blocks must be identical.
+    Exact,
+    /// Ignores `\r\n` and `\n` differences.
+    LineEndings,
+    /// Ignores whitespace at the end of the lines and line endings.
+    #[default]
+    TrailingWhitespace,
+    /// Ignores all whitespace, including indentation and blank lines.
+    Whitespace,
+}
+
+impl CompareMode {
+    /// Returns true when the blocks are considered equal.
+    #[must_use]
+    pub fn is_equal(self, a: &str, b: &str) -> bool {
+        match self {
+            Self::Exact => a == b,
+            Self::LineEndings => a.replace("\r\n", "\n") == b.replace("\r\n", "\n"),
+            Self::TrailingWhitespace => a
+                .lines()
+                .map(str::trim_end)
+                .eq(b.lines().map(str::trim_end)),
+            Self::Whitespace => {
+                let strip = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+                strip(a) == strip(b)
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SnippetTemplate {
     pub content: String,
+    /// How the template is rendered, `{snippet}` substitution by default.
+    #[serde(default, skip_serializing_if = "template::Engine::is_legacy")]
+    pub engine: template::Engine,
 }
 
 #[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 #[derive(Default, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum SnippetKind {
     Yaml,
     Code,
@@ -38,6 +167,15 @@ pub enum SnippetKind {
     Any,
 }
 
+impl SnippetKind {
+    /// Returns true when a snippet of the given kind can be taken from this
+    /// source, `Any` matches all kinds.
+    #[must_use]
+    pub fn matches(&self, kind: &Self) -> bool {
+        self == &Self::Any || self == kind
+    }
+}
+
 impl fmt::Display for SnippetKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{self:?}")
@@ -60,53 +198,164 @@ impl FromStr for SnippetKind {
 impl Snippet {
     /// Returns the snippet content, filtered based on `strip_prefix` if
     /// specified.
-    #[must_use]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the template of the placeholder can't be
+    /// rendered.
     pub fn create_content(
         &self,
         inject_actions: &injector::InjectContentAction,
         custom_templates: &BTreeMap<String, SnippetTemplate>,
-    ) -> String {
+    ) -> Result<String, template::TemplateError> {
+        let content = self.visible_content(inject_actions.nested);
+
         #[cfg(feature = "exec")]
         let content = if inject_actions.kind == injector::InjectAction::Exec {
-            exec::run(&self.content).unwrap_or_else(|err| {
+            exec::run(&content).unwrap_or_else(|err| {
                 tracing::error!(err, "execute snippet command failed");
-                self.content.to_string()
+                content
             })
         } else {
-            self.content.to_string()
+            content
         };
 
-        #[cfg(not(feature = "exec"))]
-        let content = self.content.to_string();
+        let content = if inject_actions.transform.0.is_empty() {
+            content
+        } else {
+            inject_actions.transform.apply(&content)
+        };
 
-        let content = inject_actions
-            .template
-            .before_inject(&content, custom_templates);
+        let content = match inject_actions.escape {
+            Some(escape) => escape.apply(&content),
+            None => content,
+        };
+
+        let content = inject_actions.template.before_inject(
+            &content,
+            &template::TemplateContext::new(self, &inject_actions.params),
+            custom_templates,
+        )?;
 
         let content = content
             .lines()
-            .filter_map(|line| {
-                // validate if i can remove this code
-                if line.contains("<snip") || line.contains("</snip") {
-                    return None;
-                }
+            .map(|line| {
                 let line = inject_actions.strip_prefix.as_ref().map_or_else(
                     || line.to_string(),
                     |prefix_inject| line.strip_prefix(prefix_inject).unwrap_or(line).to_string(),
                 );
 
                 if let Some(add_prefix) = &inject_actions.add_prefix {
-                    Some(format!("{add_prefix}{line}"))
+                    format!("{add_prefix}{line}")
                 } else {
-                    Some(line)
+                    line
                 }
             })
             .collect::<Vec<_>>()
             .join(crate::LINE_ENDING);
 
-        inject_actions
+        Ok(inject_actions
             .template
-            .after_inject(&content, &inject_actions.kind)
+            .after_inject(&content, &inject_actions.kind))
+    }
+
+    /// Returns the content with the nested snippets handled by the given mode
+    /// and the hidden lines removed or replaced by their ellipsis.
+    ///
+    /// A nested marker sharing its line with other text is removed from the
+    /// line, a line left without text is dropped.
+    fn visible_content(&self, mode: NestedMode) -> String {
+        if (mode == NestedMode::Keep || self.nested.is_empty()) && self.hidden.is_empty() {
+            return self.content.clone();
+        }
+
+        self.content
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                if let Some(hidden) = self
+                    .hidden
+                    .iter()
+                    .find(|hidden| (hidden.start..=hidden.end).contains(&index))
+                {
+                    return hidden.ellipsis.clone().filter(|_| index == hidden.start);
+                }
+
+                let mut spans = vec![];
+                for nested in &self.nested {
+                    let on_line = |marker: &&LineSpan| marker.line == index;
+                    match mode {
+                        NestedMode::Keep => {}
+                        // Snippets collected before the marker spans were
+                        // known only have their lines.
+                        NestedMode::Strip | NestedMode::Omit
+                            if nested.markers.is_empty()
+                                && (index == nested.open || index == nested.close) =>
+                        {
+                            return None;
+                        }
+                        NestedMode::Strip => {
+                            spans.extend(
+                                nested
+                                    .markers
+                                    .iter()
+                                    .filter(on_line)
+                                    .map(|m| m.start..m.end),
+                            );
+                        }
+                        NestedMode::Omit if nested.open == nested.close => {
+                            let markers = nested.markers.iter().filter(on_line);
+                            if let (Some(start), Some(end)) = (
+                                markers.clone().map(|marker| marker.start).min(),
+                                markers.map(|marker| marker.end).max(),
+                            ) {
+                                spans.push(start..end);
+                            }
+                        }
+                        NestedMode::Omit => {
+                            if (nested.open..=nested.close).contains(&index) {
+                                return None;
+                            }
+                        }
+                    }
+                }
+                without_spans(line, spans)
+            })
+            .collect::<Vec<_>>()
+            .join(crate::LINE_ENDING)
+    }
+}
+
+/// Removes the given byte ranges from the line, along with the whitespace
+/// separating a range from the start or the end of the line. Returns `None`
+/// when only whitespace is left.
+fn without_spans(line: &str, mut spans: Vec<Range<usize>>) -> Option<String> {
+    if spans.is_empty() {
+        return Some(line.to_string());
+    }
+
+    spans.sort_by_key(|span| span.start);
+    let ends_line = spans.iter().any(|span| span.end >= line.len());
+    let mut text = String::new();
+    let mut pos = 0;
+    for span in spans.iter().chain([&(line.len()..line.len())]) {
+        if span.start > pos {
+            let segment = &line[pos..span.start];
+            if pos > 0 && text.trim().is_empty() {
+                text.push_str(segment.trim_start());
+            } else {
+                text.push_str(segment);
+            }
+        }
+        pos = pos.max(span.end);
+    }
+
+    if text.trim().is_empty() {
+        None
+    } else if ends_line {
+        Some(text.trim_end().to_string())
+    } else {
+        Some(text)
     }
 }
 
@@ -124,17 +373,10 @@ mod tests {
     fn can_get_snippet_content_without_action() {
         let snippet = tests_cfg::get_snippet();
 
-        let action = injector::InjectContentAction {
-            kind: InjectAction::Copy,
-            snippet_id: "id".to_string(),
-            inject_from: SnippetKind::Any,
-            strip_prefix: None,
-            add_prefix: None,
-            template: Template::default(),
-        };
+        let action = injector::InjectContentAction::default();
 
         with_settings!({filters => tests_cfg::redact::all()}, {
-            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()));
+            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()).unwrap());
         });
     }
 
@@ -143,16 +385,12 @@ mod tests {
         let snippet = tests_cfg::get_snippet();
 
         let action = injector::InjectContentAction {
-            kind: InjectAction::Copy,
-            snippet_id: "id".to_string(),
-            inject_from: SnippetKind::Any,
-            strip_prefix: None,
-            add_prefix: None,
             template: Template::new("```sh\n{snippet}\n```"),
+            ..injector::InjectContentAction::default()
         };
 
         with_settings!({filters => tests_cfg::redact::all()}, {
-            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()));
+            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()).unwrap());
         });
     }
 
@@ -161,16 +399,12 @@ mod tests {
         let snippet = tests_cfg::get_snippet();
 
         let action = injector::InjectContentAction {
-            kind: InjectAction::Copy,
-            snippet_id: "id".to_string(),
-            inject_from: SnippetKind::Any,
-            strip_prefix: None,
-            add_prefix: None,
             template: Template::new("CUSTOM_ID_1"),
+            ..injector::InjectContentAction::default()
         };
 
         with_settings!({filters => tests_cfg::redact::all()}, {
-            assert_debug_snapshot!(snippet.create_content(&action, &tests_cfg::get_custom_templates()));
+            assert_debug_snapshot!(snippet.create_content(&action, &tests_cfg::get_custom_templates()).unwrap());
         });
     }
 
@@ -179,16 +413,12 @@ mod tests {
         let snippet = tests_cfg::get_snippet();
 
         let action = injector::InjectContentAction {
-            kind: InjectAction::Copy,
-            snippet_id: "id".to_string(),
-            inject_from: SnippetKind::Any,
             strip_prefix: Some("$ ".to_string()),
-            add_prefix: None,
-            template: Template::default(),
+            ..injector::InjectContentAction::default()
         };
 
         with_settings!({filters => tests_cfg::redact::all()}, {
-            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()));
+            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()).unwrap());
         });
     }
 
@@ -197,16 +427,12 @@ mod tests {
         let snippet = tests_cfg::get_snippet();
 
         let action = injector::InjectContentAction {
-            kind: InjectAction::Copy,
-            snippet_id: "id".to_string(),
-            inject_from: SnippetKind::Any,
-            strip_prefix: None,
             add_prefix: Some("$".to_string()),
-            template: Template::default(),
+            ..injector::InjectContentAction::default()
         };
 
         with_settings!({filters => tests_cfg::redact::all()}, {
-            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()));
+            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()).unwrap());
         });
     }
 
@@ -215,16 +441,14 @@ mod tests {
         let snippet = tests_cfg::get_snippet();
 
         let action = injector::InjectContentAction {
-            kind: InjectAction::Copy,
-            snippet_id: "id".to_string(),
-            inject_from: SnippetKind::Any,
             strip_prefix: Some("$ ".to_string()),
             add_prefix: Some("- ".to_string()),
             template: Template::new("```sh\n{snippet}\n```"),
+            ..injector::InjectContentAction::default()
         };
 
         with_settings!({filters => tests_cfg::redact::all()}, {
-            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()));
+            assert_debug_snapshot!(snippet.create_content(&action, &BTreeMap::new()).unwrap());
         });
     }
 
@@ -236,16 +460,13 @@ mod tests {
 
         let action = injector::InjectContentAction {
             kind: InjectAction::Exec,
-            snippet_id: "id".to_string(),
-            inject_from: SnippetKind::Any,
-            strip_prefix: None,
-            add_prefix: None,
             template: Template::new("```sh\n{snippet}\n```"),
+            ..injector::InjectContentAction::default()
         };
 
         assert_debug_snapshot!(
             "unix_can_get_snippet_with_exec_action_with_template",
-            snippet.create_content(&action, &BTreeMap::new())
+            snippet.create_content(&action, &BTreeMap::new()).unwrap()
         );
     }
 
@@ -257,16 +478,159 @@ mod tests {
 
         let action = injector::InjectContentAction {
             kind: InjectAction::Exec,
-            snippet_id: "id".to_string(),
-            inject_from: SnippetKind::Any,
-            strip_prefix: None,
-            add_prefix: None,
             template: Template::new("```sh\n{snippet}\n```"),
+            ..injector::InjectContentAction::default()
         };
 
         assert_debug_snapshot!(
             "windows_can_get_snippet_with_exec_action_with_template",
-            snippet.create_content(&action, &BTreeMap::new())
+            snippet.create_content(&action, &BTreeMap::new()).unwrap()
         );
     }
+
+    #[test]
+    fn can_get_snippet_content_without_hidden_lines() {
+        let mut snippet = tests_cfg::get_snippet();
+        snippet.content = r#"fn main() {
+    let config = setup(); // snip:hide
+    // snip:hide-start
+    assert!(config.valid);
+    // snip:hide-end
+    let data = load(&config).unwrap(); // snip:...
+    // <snip id="inner">
+    run(data);
+    // </snip>
+}"#
+        .to_string();
+        snippet.nested = vec![NestedLines {
+            open: 6,
+            close: 8,
+            markers: vec![],
+        }];
+        snippet.hidden = vec![
+            HiddenLines {
+                start: 1,
+                end: 1,
+                ellipsis: None,
+            },
+            HiddenLines {
+                start: 2,
+                end: 4,
+                ellipsis: None,
+            },
+            HiddenLines {
+                start: 5,
+                end: 5,
+                ellipsis: Some("    // ...".to_string()),
+            },
+        ];
+
+        let action = injector::InjectContentAction {
+            nested: NestedMode::Keep,
+            ..injector::InjectContentAction::default()
+        };
+
+        assert_eq!(
+            snippet.create_content(&action, &BTreeMap::new()).unwrap(),
+            "fn main() {\n    // ...\n    // <snip id=\"inner\">\n    run(data);\n    // </snip>\n}"
+        );
+    }
+
+    #[test]
+    fn can_get_snippet_content_with_nested_modes() {
+        let mut snippet = tests_cfg::get_snippet();
+        snippet.content = r#"outer
+// <snip id="inner">
+inner
+// </snip>
+mentions <snip id="x"> in text"#
+            .to_string();
+        snippet.nested = vec![NestedLines {
+            open: 1,
+            close: 3,
+            markers: vec![
+                LineSpan {
+                    line: 1,
+                    start: 0,
+                    end: 20,
+                },
+                LineSpan {
+                    line: 3,
+                    start: 0,
+                    end: 10,
+                },
+            ],
+        }];
+
+        let contents = [NestedMode::Strip, NestedMode::Keep, NestedMode::Omit].map(|nested| {
+            let action = injector::InjectContentAction {
+                kind: InjectAction::Copy,
+                snippet_id: "id".to_string(),
+                inject_from: SnippetKind::Any,
+                prefer: None,
+                strip_prefix: None,
+                add_prefix: None,
+                nested,
+                transform: transform::Pipeline::default(),
+                escape: None,
+                params: BTreeMap::new(),
+                template: Template::default(),
+            };
+            (
+                nested,
+                snippet.create_content(&action, &BTreeMap::new()).unwrap(),
+            )
+        });
+
+        with_settings!({filters => tests_cfg::redact::all()}, {
+            assert_debug_snapshot!(contents);
+        });
+    }
+
+    #[test]
+    fn can_strip_inline_nested_snippets() {
+        let content = r#"<!-- <snip id="outer"> -->
+Install v<!--<snip id="version">-->1.2.0<!--</snip>--> today.
+<!-- <snip id="overlap"> --> Overlapping text
+<!-- </snip id="outer"> -->
+<!-- </snip id="overlap"> -->
+"#;
+        let document = document::Document::parse(content, &syntax::Syntax::default()).unwrap();
+        let outer = &document.blocks[0];
+        let snippet = Snippet {
+            content: document.content_lines(outer).join("\n"),
+            nested: document.nested_lines(outer),
+            ..tests_cfg::get_snippet()
+        };
+
+        let contents = [NestedMode::Strip, NestedMode::Keep, NestedMode::Omit]
+            .map(|nested| snippet.visible_content(nested));
+        assert_eq!(
+            contents,
+            [
+                "Install v1.2.0 today.\nOverlapping text",
+                "Install v<!--<snip id=\"version\">-->1.2.0<!--</snip>--> today.\n<!-- <snip id=\"overlap\"> --> Overlapping text",
+                "Install v today.\nOverlapping text",
+            ]
+        );
+    }
+
+    #[test]
+    fn can_compare_blocks() {
+        let block = "<!-- <snip> -->\n  snipdoc run\n<!-- </snip> -->";
+
+        assert!(CompareMode::Exact.is_equal(block, block));
+        assert!(!CompareMode::Exact.is_equal(block, &block.replace('\n', "\r\n")));
+        assert!(CompareMode::LineEndings.is_equal(block, &block.replace('\n', "\r\n")));
+        assert!(!CompareMode::LineEndings.is_equal(block, &block.replace("run", "run ")));
+        assert!(CompareMode::TrailingWhitespace.is_equal(block, &block.replace("run", "run ")));
+        assert!(CompareMode::TrailingWhitespace
+            .is_equal(block, &block.replace('\n', "\r\n").replace("run", "run\t")));
+        assert!(!CompareMode::TrailingWhitespace
+            .is_equal(block, &block.replace("  snipdoc", "snipdoc")));
+        assert!(!CompareMode::TrailingWhitespace.is_equal(block, &block.replace("\n", "\n\n")));
+        assert!(CompareMode::Whitespace.is_equal(block, &block.replace("  snipdoc", "snipdoc")));
+        assert!(CompareMode::Whitespace.is_equal(block, &block.replace("\n", "\n\n")));
+        assert!(!CompareMode::Whitespace.is_equal(block, &block.replace("run", "check")));
+    }
 }
@@ -0,0 +1,337 @@
+//! Renames a snippet id in the snip tags of the files, keeping the rest of
+//! the files as written.
+//!
+//! The snippet is named by its qualified id, e.g. `crate_a::installation`.
+//! Only the tags defining it and the placeholders resolving to it are
+//! renamed, in the form they are written in: `installation`,
+//! `crate_a::installation` or `./crates/a#installation`.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use super::tag::is_valid_id;
+use super::{
+    document::Document,
+    syntax::{Syntax, SyntaxResolver, NAMESPACE_SEPARATOR},
+    SnippetKind,
+};
+use crate::{
+    config::Config,
+    db::{DBData, Scope},
+    errors::{Diagnostic, ParserResult},
+    read_file::RFile,
+    walk::Walk,
+};
+
+pub struct Renamer<'a> {
+    /// The folder of the renamed file, `./dir#id` references are relative to
+    /// it.
+    pub base_folder: &'a Path,
+    pub input: &'a str,
+    pub syntax: &'a Syntax,
+    pub db_data: &'a DBData,
+}
+
+/// A file in which the snippet id was renamed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Renamed {
+    /// The content of the file before the rename.
+    pub original: String,
+    /// The content of the file after the rename.
+    pub content: String,
+    /// The number of tags renamed in the file.
+    pub count: usize,
+}
+
+/// The result of renaming a snippet id in the files of a folder.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenameResults {
+    pub root_folder: PathBuf,
+    /// Files with at least one renamed tag.
+    pub files: BTreeMap<PathBuf, Renamed>,
+    /// Files that could not be read or parsed, they are not renamed.
+    pub errors: BTreeMap<PathBuf, Diagnostic>,
+}
+
+impl<'a> Renamer<'a> {
+    #[must_use]
+    pub const fn new(
+        base_folder: &'a Path,
+        input: &'a str,
+        syntax: &'a Syntax,
+        db_data: &'a DBData,
+    ) -> Self {
+        Self {
+            base_folder,
+            input,
+            syntax,
+            db_data,
+        }
+    }
+
+    /// Renames the snippet with the qualified id `old` to the qualified id
+    /// `new` in all the files of the given `Walk`.
+    #[must_use]
+    pub fn walk(
+        walk: &Walk,
+        config: &Config,
+        db_data: &DBData,
+        old: &str,
+        new: &str,
+    ) -> RenameResults {
+        let resolver = SyntaxResolver::new(config);
+        let results = walk
+            .get_files()
+            .par_iter()
+            .map(|path| {
+                let syntax =
+                    resolver.resolve(path.strip_prefix(&walk.folder).unwrap_or(path));
+                let base_folder = path.parent().unwrap_or(walk.folder.as_path());
+                let result = Self::file(path, base_folder, &syntax, db_data, old, new)
+                    .map_err(|err| {
+                    tracing::debug!(path = %path.display(), err = %err, "could not rename snippets");
+                    Diagnostic::from(&err).with_path(path)
+                });
+                (path.clone(), result)
+            })
+            .collect::<Vec<_>>();
+
+        let mut files = BTreeMap::new();
+        let mut errors = BTreeMap::new();
+        for (path, result) in results {
+            match result {
+                Ok(Some(renamed)) => {
+                    files.insert(path, renamed);
+                }
+                Ok(None) => (),
+                Err(diagnostic) => {
+                    errors.insert(path, diagnostic);
+                }
+            }
+        }
+
+        RenameResults {
+            root_folder: walk.folder.clone(),
+            files,
+            errors,
+        }
+    }
+
+    fn file(
+        path: &Path,
+        base_folder: &Path,
+        syntax: &Syntax,
+        db_data: &DBData,
+        old: &str,
+        new: &str,
+    ) -> ParserResult<'static, Option<Renamed>> {
+        let r_file = RFile::new(path)?;
+        let Some((content, count)) =
+            Renamer::new(base_folder, &r_file.content, syntax, db_data).run(old, new)?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(Renamed {
+            original: r_file.content,
+            content,
+            count,
+        }))
+    }
+
+    /// Replaces the local id of the `id` attribute in the open, close and
+    /// placeholder tags referring to the snippet with the qualified id `old`
+    /// by the local id of `new`. The quotes, the namespace or folder of the
+    /// reference and the rest of the input are kept as is.
+    ///
+    /// Returns the renamed input and the number of renamed tags, or `None`
+    /// when no tag refers to the snippet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the input can't be parsed.
+    pub fn run(&self, old: &str, new: &str) -> ParserResult<'_, Option<(String, usize)>> {
+        let document = Document::parse(self.input, self.syntax)?;
+
+        // A placeholder has the same open and close marker, the spans dedupe
+        // its id.
+        let spans = document
+            .blocks
+            .iter()
+            .filter(|block| self.refers_to(&block.open.tag.attributes_map(), old))
+            .flat_map(|block| [&block.open.tag, &block.close.tag])
+            .filter_map(|tag| tag.get("id"))
+            .filter_map(|id| {
+                let span = id.value_span.as_ref()?;
+                // Skip the opening quote and keep the closing one.
+                Some((span.start + 1 + local_id_start(&id.value), span.end - 1))
+            })
+            .collect::<BTreeSet<_>>();
+
+        if spans.is_empty() {
+            return Ok(None);
+        }
+
+        let new = &new[local_id_start(new)..];
+        let mut content = String::with_capacity(self.input.len());
+        let mut pos = 0;
+        for (start, end) in &spans {
+            content.push_str(&self.input[pos..*start]);
+            content.push_str(new);
+            pos = *end;
+        }
+        content.push_str(&self.input[pos..]);
+
+        Ok(Some((content, spans.len())))
+    }
+
+    /// Whether the tag attributes define the snippet with the qualified id,
+    /// or are a placeholder resolving to it. A placeholder resolving through
+    /// an alias keeps the alias.
+    fn refers_to(&self, attributes: &BTreeMap<String, String>, qualified_id: &str) -> bool {
+        let Some(id) = attributes.get("id") else {
+            return false;
+        };
+        let namespace = attributes.get("namespace").map(String::as_str);
+
+        if attributes.contains_key("inject_from") {
+            let scope = Scope {
+                namespace: namespace.or(self.syntax.namespace.as_deref()),
+                root: self.base_folder,
+            };
+            self.db_data
+                .get(id, &scope, &SnippetKind::Any, None)
+                .is_some_and(|resolved| {
+                    resolved.alias.is_none() && resolved.snippet.id == qualified_id
+                })
+        } else {
+            self.syntax.qualify(id, namespace) == qualified_id
+        }
+    }
+}
+
+/// Returns where the local id starts in a snippet id, after the folder of a
+/// `./dir#id` reference or the namespace of a `ns::id`.
+fn local_id_start(id: &str) -> usize {
+    id.strip_prefix("./")
+        .and_then(|reference| reference.find('#'))
+        .map_or_else(
+            || {
+                id.rfind(NAMESPACE_SEPARATOR)
+                    .map_or(0, |pos| pos + NAMESPACE_SEPARATOR.len())
+            },
+            |pos| "./".len() + pos + 1,
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot;
+
+    use super::*;
+    use crate::{db::Definition, parser::Snippet};
+
+    fn db_data(snippets: &[(&str, &str)]) -> DBData {
+        let mut db_data = DBData::default();
+        for (id, path) in snippets {
+            db_data.insert(
+                Snippet {
+                    id: (*id).to_string(),
+                    content: String::new(),
+                    kind: SnippetKind::Code,
+                    path: PathBuf::from(path),
+                    nested: vec![],
+                    hidden: vec![],
+                    lines: None,
+                    aliases: vec![],
+                    deprecated: None,
+                },
+                Definition {
+                    kind: SnippetKind::Code,
+                    path: PathBuf::from(path),
+                    line: None,
+                    allow_override: false,
+                },
+            );
+        }
+        db_data
+    }
+
+    #[test]
+    fn can_rename() {
+        let content = r#"# Rename
+<!-- <snip id="installation"> -->
+cargo install snipdoc
+<!-- </snip id="installation"> -->
+
+<!--<snip id='installation' inject_from="code"/>-->
+
+// <snip id="installation-guide">
+// <snip id="other"> installation </snip>
+// </snip>
+
+```md
+<!-- <snip id="installation"> -->
+```
+"#;
+        let syntax = Syntax {
+            markdown: true,
+            fenced_examples: true,
+            ..Syntax::default()
+        };
+        let db_data = db_data(&[("installation", "README.md")]);
+        let renamer = Renamer::new(Path::new("."), content, &syntax, &db_data);
+
+        let (renamed, count) = renamer.run("installation", "install").unwrap().unwrap();
+        assert_eq!(count, 3);
+        assert_snapshot!(renamed);
+
+        assert!(renamer.run("not-found", "install").unwrap().is_none());
+    }
+
+    #[test]
+    fn can_rename_namespaced_references() {
+        let content = r#"// <snip id="installation">
+// cargo add crate_a
+// </snip id="installation">
+
+// <snip id="installation" inject_from="code" />
+// <snip id="crate_a::installation" inject_from="code" />
+// <snip id="./crates/a#installation" inject_from="code" />
+// <snip id="crate_b::installation" inject_from="code" />
+// <snip id="installation" inject_from="code" namespace="crate_b" />
+"#;
+        let syntax = Syntax {
+            namespace: Some("crate_a".to_string()),
+            ..Syntax::default()
+        };
+        let db_data = db_data(&[
+            ("crate_a::installation", "crates/a/lib.rs"),
+            ("crate_b::installation", "crates/b/lib.rs"),
+        ]);
+        let renamer = Renamer::new(Path::new("."), content, &syntax, &db_data);
+
+        let (renamed, count) = renamer
+            .run("crate_a::installation", "crate_a::install")
+            .unwrap()
+            .unwrap();
+        assert_eq!(count, 5);
+        assert_eq!(
+            renamed,
+            r#"// <snip id="install">
+// cargo add crate_a
+// </snip id="install">
+
+// <snip id="install" inject_from="code" />
+// <snip id="crate_a::install" inject_from="code" />
+// <snip id="./crates/a#install" inject_from="code" />
+// <snip id="crate_b::installation" inject_from="code" />
+// <snip id="installation" inject_from="code" namespace="crate_b" />
+"#
+        );
+    }
+}
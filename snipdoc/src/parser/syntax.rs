@@ -0,0 +1,384 @@
+//! Resolves the comment syntax that wraps snip tags for a given file.
+use std::path::Path;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use super::escape::Escape;
+use crate::config::{CommentToken, Config};
+
+pub const DEFAULT_KEYWORD: &str = "snip";
+
+/// Separates the namespace from the id, e.g. `crate_a::installation`.
+pub const NAMESPACE_SEPARATOR: &str = "::";
+
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdx"];
+
+/// The rules used to recognize and name snip tags in a single file.
+#[derive(Debug, Clone)]
+pub struct Syntax {
+    /// Comment tokens that may wrap a snip tag.
+    pub comments: Vec<CommentToken>,
+    /// Whether tags inside markdown code spans are ignored.
+    pub markdown: bool,
+    /// Whether tags inside markdown fenced code blocks are ignored as well.
+    pub fenced_examples: bool,
+    /// Whether tags alone on a line are recognized without a comment token.
+    pub bare: bool,
+    /// The element name of the tags, e.g. `snip` in `<snip id="x">`.
+    pub keyword: String,
+    /// The namespace of the snippets defined in the file.
+    pub namespace: Option<String>,
+    /// How the snippets injected in the file are escaped by default.
+    pub escape: Option<Escape>,
+}
+
+impl Default for Syntax {
+    fn default() -> Self {
+        Self {
+            comments: vec![
+                CommentToken::block("<!--", "-->"),
+                CommentToken::block("/**", "*/"),
+                CommentToken::block("/*", "*/"),
+                CommentToken::block("(*", "*)"),
+                CommentToken::line("///"),
+                CommentToken::line("//"),
+                CommentToken::line("#"),
+            ],
+            markdown: false,
+            fenced_examples: false,
+            bare: false,
+            keyword: DEFAULT_KEYWORD.to_string(),
+            namespace: None,
+            escape: None,
+        }
+    }
+}
+
+impl Syntax {
+    /// Resolves the syntax of the given file from the configuration.
+    ///
+    /// The file patterns are compiled on every call, use [`SyntaxResolver`]
+    /// when resolving many files.
+    #[must_use]
+    pub fn from_config(config: &Config, path: &Path) -> Self {
+        SyntaxResolver::new(config).resolve(path)
+    }
+
+    /// Returns the id of a snippet defined in this file, qualified with the
+    /// given namespace or the namespace of the file.
+    #[must_use]
+    pub fn qualify(&self, id: &str, namespace: Option<&str>) -> String {
+        match namespace.or(self.namespace.as_deref()) {
+            Some(namespace) => format!("{namespace}{NAMESPACE_SEPARATOR}{id}"),
+            None => id.to_string(),
+        }
+    }
+
+    /// Finds the comment token that ends the given text, preferring the
+    /// longest match. For example, `///` wins over `//`.
+    #[must_use]
+    pub fn comment_ending(&self, text: &str) -> Option<&CommentToken> {
+        self.comments
+            .iter()
+            .filter(|token| !token.open.is_empty() && text.ends_with(token.open.as_str()))
+            .max_by_key(|token| token.open.len())
+    }
+}
+
+/// Resolves the syntax of the walked files, with the file patterns of the
+/// configuration compiled once.
+pub struct SyntaxResolver<'a> {
+    config: &'a Config,
+    comments: FilePatterns,
+    bare_tags: FilePatterns,
+    fenced_examples: FilePatterns,
+    escape: FilePatterns,
+}
+
+impl<'a> SyntaxResolver<'a> {
+    #[must_use]
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            comments: FilePatterns::new(config.comments.keys()),
+            bare_tags: FilePatterns::new(&config.bare_tags),
+            fenced_examples: FilePatterns::new(&config.fenced_examples),
+            escape: FilePatterns::new(config.inject.escape.keys()),
+        }
+    }
+
+    /// Resolves the syntax of the given file from the `comments` section of
+    /// the configuration.
+    ///
+    /// All entries matching the file are combined. When no entry matches, the
+    /// built-in comment tokens are used. Markdown files are detected by their
+    /// extension, fenced code blocks are only ignored in the markdown files
+    /// matching `fenced_examples`.
+    #[must_use]
+    pub fn resolve(&self, path: &Path) -> Syntax {
+        let config = self.config;
+        let markdown = MARKDOWN_EXTENSIONS
+            .iter()
+            .any(|ext| has_extension(path, ext));
+        let fenced_examples = markdown && !self.fenced_examples.matches(path).is_empty();
+        let bare = !self.bare_tags.matches(path).is_empty();
+        let keyword = config
+            .keyword
+            .clone()
+            .unwrap_or_else(|| DEFAULT_KEYWORD.to_string());
+        let namespace = config
+            .namespaces
+            .iter()
+            .map(|(dir, namespace)| (Path::new(dir.trim_start_matches("./")), namespace))
+            .filter(|(dir, _)| path.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.components().count())
+            .map(|(_, namespace)| namespace.clone());
+        let escape = self
+            .escape
+            .matches(path)
+            .first()
+            .and_then(|index| config.inject.escape.values().nth(*index))
+            .copied();
+        let matched = self.comments.matches(path);
+        let comments = config
+            .comments
+            .values()
+            .enumerate()
+            .filter(|(index, _)| matched.contains(index))
+            .flat_map(|(_, tokens)| tokens.iter().cloned())
+            .collect::<Vec<_>>();
+
+        if comments.is_empty() {
+            Syntax {
+                markdown,
+                fenced_examples,
+                bare,
+                keyword,
+                namespace,
+                escape,
+                ..Syntax::default()
+            }
+        } else {
+            Syntax {
+                comments,
+                markdown,
+                fenced_examples,
+                bare,
+                keyword,
+                namespace,
+                escape,
+            }
+        }
+    }
+}
+
+/// File patterns of a configuration section. Patterns containing glob
+/// characters are matched against the path, other patterns are treated as a
+/// file extension.
+struct FilePatterns {
+    globs: GlobSet,
+    /// The pattern index of every glob in `globs`.
+    glob_indexes: Vec<usize>,
+    /// The extension and pattern index of the other patterns.
+    extensions: Vec<(String, usize)>,
+}
+
+impl FilePatterns {
+    fn new<'p>(patterns: impl IntoIterator<Item = &'p String>) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        let mut glob_indexes = vec![];
+        let mut extensions = vec![];
+        for (index, pattern) in patterns.into_iter().enumerate() {
+            if pattern.contains(['*', '?', '[', '{']) {
+                match Glob::new(pattern) {
+                    Ok(glob) => {
+                        builder.add(glob);
+                        glob_indexes.push(index);
+                    }
+                    Err(err) => {
+                        tracing::warn!(pattern, err = %err, "invalid file pattern");
+                    }
+                }
+            } else {
+                extensions.push((pattern.trim_start_matches('.').to_string(), index));
+            }
+        }
+
+        let globs = builder.build().unwrap_or_else(|err| {
+            tracing::warn!(err = %err, "could not build file patterns");
+            GlobSet::empty()
+        });
+        Self {
+            globs,
+            glob_indexes,
+            extensions,
+        }
+    }
+
+    /// Returns the indexes of the patterns matching the file, in order.
+    fn matches(&self, path: &Path) -> Vec<usize> {
+        let mut matches = self
+            .globs
+            .matches(path)
+            .into_iter()
+            .map(|glob| self.glob_indexes[glob])
+            .chain(
+                self.extensions
+                    .iter()
+                    .filter(|(extension, _)| has_extension(path, extension))
+                    .map(|(_, index)| *index),
+            )
+            .collect::<Vec<_>>();
+        matches.sort_unstable();
+        matches
+    }
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.to_string_lossy().eq_ignore_ascii_case(extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::config::InjectConfig;
+
+    #[test]
+    fn can_resolve_from_config() {
+        let config = Config {
+            comments: BTreeMap::from([
+                ("*.sql".to_string(), vec![CommentToken::line("--")]),
+                (
+                    "lua".to_string(),
+                    vec![CommentToken::line("--"), CommentToken::block("--[[", "]]")],
+                ),
+            ]),
+            ..Config::default()
+        };
+
+        assert_eq!(
+            Syntax::from_config(&config, Path::new("db/schema.sql")).comments,
+            vec![CommentToken::line("--")]
+        );
+        assert_eq!(
+            Syntax::from_config(&config, Path::new("init.LUA"))
+                .comments
+                .len(),
+            2
+        );
+        assert_eq!(
+            Syntax::from_config(&config, Path::new("README.md")).comments,
+            Syntax::default().comments
+        );
+        assert!(Syntax::from_config(&config, Path::new("README.md")).markdown);
+        assert!(!Syntax::from_config(&config, Path::new("main.rs")).markdown);
+    }
+
+    #[test]
+    fn can_resolve_fenced_examples() {
+        let config = Config {
+            fenced_examples: vec!["docs/**/*.md".to_string(), "rs".to_string()],
+            ..Config::default()
+        };
+        let fenced_examples =
+            |path: &str| Syntax::from_config(&config, Path::new(path)).fenced_examples;
+
+        assert!(fenced_examples("docs/inject/README.md"));
+        assert!(!fenced_examples("README.md"));
+        assert!(!fenced_examples("main.rs"));
+    }
+
+    #[test]
+    fn can_resolve_bare_tags() {
+        let config = Config {
+            bare_tags: vec!["*.txt".to_string(), "py".to_string()],
+            ..Config::default()
+        };
+
+        assert!(Syntax::from_config(&config, Path::new("docs/notes.txt")).bare);
+        assert!(Syntax::from_config(&config, Path::new("main.py")).bare);
+        assert!(!Syntax::from_config(&config, Path::new("main.rs")).bare);
+    }
+
+    #[test]
+    fn can_resolve_keyword() {
+        let config = Config {
+            keyword: Some("doc-snippet".to_string()),
+            ..Config::default()
+        };
+
+        assert_eq!(
+            Syntax::from_config(&config, Path::new("README.md")).keyword,
+            "doc-snippet"
+        );
+        assert_eq!(
+            Syntax::from_config(&Config::default(), Path::new("README.md")).keyword,
+            DEFAULT_KEYWORD
+        );
+    }
+
+    #[test]
+    fn can_resolve_namespace() {
+        let config = Config {
+            namespaces: BTreeMap::from([
+                ("crates/a".to_string(), "crate_a".to_string()),
+                ("./crates/a/nested".to_string(), "nested".to_string()),
+            ]),
+            ..Config::default()
+        };
+        let namespace = |path: &str| Syntax::from_config(&config, Path::new(path)).namespace;
+
+        assert_eq!(namespace("crates/a/README.md"), Some("crate_a".to_string()));
+        assert_eq!(
+            namespace("crates/a/nested/lib.rs"),
+            Some("nested".to_string())
+        );
+        assert_eq!(namespace("crates/ab/README.md"), None);
+
+        let syntax = Syntax::from_config(&config, Path::new("crates/a/README.md"));
+        assert_eq!(syntax.qualify("install", None), "crate_a::install");
+        assert_eq!(syntax.qualify("install", Some("other")), "other::install");
+    }
+
+    #[test]
+    fn can_resolve_escape() {
+        let config = Config {
+            inject: InjectConfig {
+                escape: BTreeMap::from([
+                    ("html".to_string(), Escape::Html),
+                    ("docs/*.json".to_string(), Escape::Json),
+                ]),
+                ..InjectConfig::default()
+            },
+            ..Config::default()
+        };
+        let escape = |path: &str| Syntax::from_config(&config, Path::new(path)).escape;
+
+        assert_eq!(escape("site/index.html"), Some(Escape::Html));
+        assert_eq!(escape("docs/config.json"), Some(Escape::Json));
+        assert_eq!(escape("config.json"), None);
+        assert_eq!(escape("README.md"), None);
+    }
+
+    #[test]
+    fn can_find_longest_comment_ending() {
+        let syntax = Syntax::default();
+
+        assert_eq!(
+            syntax.comment_ending("    ///").map(|t| t.open.as_str()),
+            Some("///")
+        );
+        assert_eq!(
+            syntax.comment_ending("<!--").map(|t| t.open.as_str()),
+            Some("<!--")
+        );
+        assert_eq!(
+            syntax.comment_ending("/**").map(|t| t.open.as_str()),
+            Some("/**")
+        );
+        assert!(syntax.comment_ending("text").is_none());
+    }
+}
@@ -0,0 +1,404 @@
+//! Parses snip tags and their attributes.
+//!
+//! Attributes keep their byte span in the input, so errors can point at the
+//! offending attribute and the tag can be rewritten without touching the
+//! quoting of the other attributes.
+use std::{borrow::Cow, collections::BTreeMap, ops::Range};
+
+use pest::{iterators::Pair, Parser};
+
+use super::{Rule, SnippetParse};
+use crate::errors::{Diagnostic, ParserResult, Position};
+
+/// The attributes snipdoc understands, others are reported as a warning
+/// unless the tag has a template, which gets them as variables.
+pub const KNOWN_ATTRIBUTES: &[&str] = &[
+    "id",
+    "inject_from",
+    "action",
+    "strip_prefix",
+    "add_prefix",
+    "template",
+    "nested",
+    "allow_override",
+    "prefer",
+    "namespace",
+    "aliases",
+    "deprecated",
+    "transform",
+    "escape",
+];
+
+/// An attribute of a snip tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    pub name: String,
+    /// The unescaped value, empty when the attribute has no value.
+    pub value: String,
+    /// The whole attribute, e.g. `id="x"`.
+    pub span: Range<usize>,
+    /// The value as written in the tag, including the quotes.
+    pub value_span: Option<Range<usize>>,
+}
+
+/// A snip tag found in the input.
+#[derive(Debug, Clone)]
+pub struct Tag {
+    /// The tag, from `<` to `>`.
+    pub span: Range<usize>,
+    pub attributes: Vec<Attribute>,
+    /// Whether the tag is a self-closing placeholder, e.g. `<snip id="x"/>`.
+    pub self_closing: bool,
+}
+
+impl Tag {
+    /// Parses the open tag named `keyword` starting at `at`.
+    ///
+    /// Returns `Ok(None)` when the input at `at` is not a snip tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the tag is malformed, has no valid `id` or sets an
+    /// attribute twice.
+    pub fn parse_open<'a>(input: &str, at: usize, keyword: &str) -> ParserResult<'a, Option<Self>> {
+        let name = format!("<{keyword}");
+        if !is_tag_start(&input[at..], &name) {
+            return Ok(None);
+        }
+
+        let Ok(pairs) = SnippetParse::parse(Rule::tag, &input[at..]) else {
+            return Err(malformed_attribute(input, at + name.len()).into());
+        };
+
+        let tag = Self {
+            span: at..at + pairs.as_str().len(),
+            self_closing: pairs.as_str().ends_with("/>"),
+            attributes: pairs
+                .flatten()
+                .filter(|pair| matches!(pair.as_rule(), Rule::element_id | Rule::attribute))
+                .map(|pair| attribute(pair, at))
+                .collect(),
+        };
+        tag.validate(input, keyword)?;
+
+        Ok(Some(tag))
+    }
+
+    /// Parses the close tag named `keyword` starting at `at`.
+    ///
+    /// Returns `Ok(None)` when the input at `at` is not a snip close tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the close tag is malformed.
+    pub fn parse_close<'a>(
+        input: &str,
+        at: usize,
+        keyword: &str,
+    ) -> ParserResult<'a, Option<Self>> {
+        if !is_tag_start(&input[at..], &format!("</{keyword}")) {
+            return Ok(None);
+        }
+
+        let Ok(pairs) = SnippetParse::parse(Rule::tag_close, &input[at..]) else {
+            let end = input[at..]
+                .find(['>', '\n'])
+                .map_or(input.len(), |i| at + i + 1);
+            return Err(Diagnostic::new("malformed close tag")
+                .at(input, at..end.min(input.len()))
+                .with_hint(&format!(
+                    "expected `</{keyword}>` or `</{keyword} id=\"...\">`"
+                ))
+                .into());
+        };
+
+        Ok(Some(Self {
+            span: at..at + pairs.as_str().len(),
+            self_closing: false,
+            attributes: pairs
+                .flatten()
+                .filter(|pair| pair.as_rule() == Rule::element_id)
+                .map(|pair| attribute(pair, at))
+                .collect(),
+        }))
+    }
+
+    /// Returns the attribute with the given name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|attr| attr.name == name)
+    }
+
+    /// Returns the snippet id of the tag.
+    #[must_use]
+    pub fn id(&self) -> Option<&str> {
+        self.get("id").map(|attr| attr.value.as_str())
+    }
+
+    /// Returns the text of a self-closing tag as an open tag, keeping the
+    /// attributes as written.
+    #[must_use]
+    pub fn open_text<'i>(&self, input: &'i str) -> Cow<'i, str> {
+        let text = &input[self.span.clone()];
+        text.strip_suffix("/>").map_or(Cow::Borrowed(text), |text| {
+            Cow::Owned(format!("{}>", text.trim_end()))
+        })
+    }
+
+    /// Returns the attributes as a name to value map.
+    #[must_use]
+    pub fn attributes_map(&self) -> BTreeMap<String, String> {
+        self.attributes
+            .iter()
+            .map(|attr| (attr.name.clone(), attr.value.clone()))
+            .collect()
+    }
+
+    fn validate<'a>(&self, input: &str, keyword: &str) -> ParserResult<'a, ()> {
+        for (index, attr) in self.attributes.iter().enumerate() {
+            if self.attributes[..index]
+                .iter()
+                .any(|other| other.name == attr.name)
+            {
+                return Err(
+                    Diagnostic::new(&format!("attribute `{}` is set twice", attr.name))
+                        .at(input, attr.span.clone())
+                        .with_hint("already set before in this tag")
+                        .into(),
+                );
+            }
+
+            if !KNOWN_ATTRIBUTES.contains(&attr.name.as_str()) && self.get("template").is_none() {
+                let position = Position::from_offset(input, attr.span.start);
+                tracing::warn!(
+                    attribute = attr.name,
+                    line = position.line,
+                    column = position.column,
+                    "unknown snip attribute"
+                );
+            }
+        }
+
+        match self.get("id") {
+            Some(id) if !is_valid_id(&id.value) => Err(Diagnostic::new(&format!(
+                "invalid snippet id `{}`",
+                id.value
+            ))
+            .at(input, id.value_span.clone().unwrap_or(id.span.clone()))
+            .with_hint("ids may contain letters, digits, `_`, `-`, `.`, `/`, `:` and `#`")
+            .into()),
+            Some(_) => Ok(()),
+            None => Err(
+                Diagnostic::new(&format!("{keyword} tag has no `id` attribute"))
+                    .at(input, self.span.clone())
+                    .with_hint("add an `id=\"...\"` attribute")
+                    .into(),
+            ),
+        }
+    }
+}
+
+/// Checks whether the text is a valid snippet id.
+#[must_use]
+pub fn is_valid_id(id: &str) -> bool {
+    SnippetParse::parse(Rule::id_value, id).is_ok_and(|pairs| pairs.as_str() == id)
+}
+
+/// Checks whether the text starts with the tag name followed by whitespace or
+/// the end of the tag, so `<snippet>` is not taken for a snip tag.
+fn is_tag_start(text: &str, name: &str) -> bool {
+    text.strip_prefix(name)
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|c| c.is_whitespace() || c == '>' || c == '/')
+}
+
+/// Points at the first attribute that can't be parsed in the tag whose
+/// attributes start at `from`.
+fn malformed_attribute(input: &str, from: usize) -> Diagnostic {
+    let valid = SnippetParse::parse(Rule::attributes, &input[from..])
+        .map_or(0, |pairs| pairs.as_str().len());
+    let rest = &input[from + valid..];
+    let start = from + valid + (rest.len() - rest.trim_start().len());
+    let end = input[start..]
+        .find(char::is_whitespace)
+        .map_or(input.len(), |i| start + i);
+
+    let bad = &input[start..end];
+    let hint = if bad.contains(['"', '\'']) {
+        "the value is not closed by a matching quote on this line"
+    } else {
+        "expected `name=\"value\"`"
+    };
+
+    Diagnostic::new("malformed attribute")
+        .at(input, start..end.max(start + 1).min(input.len()))
+        .with_hint(hint)
+}
+
+/// Builds an attribute from an `element_id` or `attribute` pair of a tag
+/// found at `offset`.
+fn attribute(pair: Pair<'_, Rule>, offset: usize) -> Attribute {
+    let to_range = |span: pest::Span<'_>| offset + span.start()..offset + span.end();
+    let span = to_range(pair.as_span());
+
+    if pair.as_rule() == Rule::element_id {
+        let id = pair.into_inner().next();
+        return Attribute {
+            name: "id".to_string(),
+            value: id
+                .as_ref()
+                .map(|p| p.as_str().to_string())
+                .unwrap_or_default(),
+            // The value span includes the quotes around the id.
+            value_span: id.map(|p| {
+                let range = to_range(p.as_span());
+                range.start - 1..range.end + 1
+            }),
+            span,
+        };
+    }
+
+    let mut inner = pair.into_inner();
+    let name = inner.next().map(|p| p.as_str()).unwrap_or_default();
+    let value = inner.next();
+
+    Attribute {
+        name: name.to_string(),
+        value: value
+            .as_ref()
+            .and_then(|value| value.clone().into_inner().next())
+            .map(|text| unescape(text.as_str()))
+            .unwrap_or_default(),
+        value_span: value.map(|value| to_range(value.as_span())),
+        span,
+    }
+}
+
+/// Resolves the `\"`, `\'` and `\\` escapes. Other backslashes are kept as
+/// is, so `\n` in a template stays a line break placeholder.
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some(escaped @ ('"' | '\'' | '\\')) => result.push(escaped),
+                Some(other) => {
+                    result.push(c);
+                    result.push(other);
+                }
+                None => result.push(c),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ParseError;
+
+    fn parse_error(input: &str) -> Diagnostic {
+        match Tag::parse_open(input, 0, "snip") {
+            Err(ParseError::Diagnostic(diagnostic)) => *diagnostic,
+            other => panic!("expected a diagnostic, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn can_parse_attributes() {
+        let tag = Tag::parse_open(
+            r#"<snip id="docs.setup/unix:sh" strip_prefix='$ ' add_prefix="say \"hi\" " template="<details>{snippet}</details>" title="Ünïcödé, (a|b) #1*" raw>"#, 0, "snip")
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            tag.attributes_map(),
+            BTreeMap::from([
+                ("id".to_string(), "docs.setup/unix:sh".to_string()),
+                ("strip_prefix".to_string(), "$ ".to_string()),
+                ("add_prefix".to_string(), "say \"hi\" ".to_string()),
+                (
+                    "template".to_string(),
+                    "<details>{snippet}</details>".to_string()
+                ),
+                ("title".to_string(), "Ünïcödé, (a|b) #1*".to_string()),
+                ("raw".to_string(), String::new()),
+            ])
+        );
+    }
+
+    #[test]
+    fn can_keep_spans_and_quoting() {
+        let input = r#"// <snip id="x" strip_prefix='$ '>"#;
+        let tag = Tag::parse_open(input, 3, "snip").unwrap().unwrap();
+
+        let strip_prefix = tag.get("strip_prefix").unwrap();
+        assert_eq!(
+            &input[tag.span.clone()],
+            r#"<snip id="x" strip_prefix='$ '>"#
+        );
+        assert_eq!(&input[strip_prefix.span.clone()], "strip_prefix='$ '");
+        assert_eq!(
+            &input[tag.get("id").unwrap().value_span.clone().unwrap()],
+            "\"x\""
+        );
+        assert_eq!(&input[strip_prefix.value_span.clone().unwrap()], "'$ '");
+    }
+
+    #[test]
+    fn can_keep_unknown_escapes() {
+        let tag = Tag::parse_open(r#"<snip id="x" template="a\nb\\c\'">"#, 0, "snip")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(tag.get("template").unwrap().value, r"a\nb\c'");
+    }
+
+    #[test]
+    fn can_parse_self_closing_tag() {
+        let input = r#"<snip id="x" inject_from="code" raw />"#;
+        let tag = Tag::parse_open(input, 0, "snip").unwrap().unwrap();
+
+        assert!(tag.self_closing);
+        assert_eq!(tag.get("raw").unwrap().value, "");
+        assert_eq!(
+            tag.open_text(input),
+            r#"<snip id="x" inject_from="code" raw>"#
+        );
+    }
+
+    #[test]
+    fn can_skip_other_tags() {
+        assert!(Tag::parse_open("<snippet>", 0, "snip").unwrap().is_none());
+        assert!(Tag::parse_close("</snippet>", 0, "snip").unwrap().is_none());
+    }
+
+    #[test]
+    fn fail_on_bad_attributes() {
+        let diagnostic = parse_error(r#"<snip id="x" inject_from="code>"#);
+        assert_eq!(diagnostic.message, "malformed attribute");
+        assert_eq!(diagnostic.tag.as_deref(), Some(r#"inject_from="code>"#));
+
+        let diagnostic = parse_error(r#"<snip id="x" action=copy>"#);
+        assert_eq!(diagnostic.tag.as_deref(), Some("action=copy>"));
+
+        let diagnostic = parse_error(r#"<snip id="x" -->"#);
+        assert_eq!(diagnostic.message, "malformed attribute");
+        assert_eq!(diagnostic.tag.as_deref(), Some("-->"));
+
+        let diagnostic = parse_error(r#"<snip id="x" action="copy" action="exec">"#);
+        assert_eq!(diagnostic.message, "attribute `action` is set twice");
+        assert_eq!(diagnostic.tag.as_deref(), Some(r#"action="exec""#));
+
+        let diagnostic = parse_error(r#"<snip id="has space">"#);
+        assert_eq!(diagnostic.message, "invalid snippet id `has space`");
+        assert_eq!(diagnostic.tag.as_deref(), Some(r#""has space""#));
+
+        let diagnostic = parse_error(r#"<snip ids="x">"#);
+        assert_eq!(diagnostic.message, "snip tag has no `id` attribute");
+    }
+}
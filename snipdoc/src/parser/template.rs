@@ -0,0 +1,411 @@
+//! Renders the templates wrapping the injected snippets.
+//!
+//! By default the `{snippet}` placeholder of a template is replaced with the
+//! snippet, every line of the snippet gets the indentation in front of the
+//! placeholder. Templates from the db opting in to the `jinja` engine use the
+//! [minijinja](https://docs.rs/minijinja) syntax and get the snippet content,
+//! its metadata and the extra attributes of the placeholder as variables.
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use lazy_static::lazy_static;
+use minijinja::{Environment, ErrorKind, UndefinedBehavior, Value};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{Snippet, SnippetKind};
+use crate::LINE_ENDING;
+
+lazy_static! {
+    static ref RE_SNIPPET_TEMPLATE_PLACEHOLDER: Regex =
+        Regex::new(r"(?m)(^\s*|)\{\s*snippet\}").unwrap();
+}
+
+/// The filter indenting the legacy `{snippet}` placeholder.
+const PREFIX_LINES_FILTER: &str = "prefix_lines";
+
+/// How a template is rendered.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    /// Replaces the `{snippet}` placeholders and the `\n` escapes, the rest
+    /// of the template is kept as written.
+    #[default]
+    Legacy,
+    /// Renders the template with minijinja.
+    Jinja,
+}
+
+impl Engine {
+    #[must_use]
+    pub fn is_legacy(&self) -> bool {
+        *self == Self::Legacy
+    }
+}
+
+/// The snippet metadata available in the templates.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub id: String,
+    pub path: String,
+    pub kind: Option<SnippetKind>,
+    /// The language of the snippet, from the extension of its file.
+    pub language: Option<String>,
+    /// The lines of the snippet content in its file, starting at 1.
+    pub line_start: Option<usize>,
+    pub line_end: Option<usize>,
+    /// The extra attributes of the placeholder, e.g. `title="Usage"`.
+    pub params: BTreeMap<String, String>,
+}
+
+/// A template that could not be rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl TemplateContext {
+    /// Collects the metadata of the snippet and the parameters given by the
+    /// placeholder.
+    #[must_use]
+    pub fn new(snippet: &Snippet, params: &BTreeMap<String, String>) -> Self {
+        Self {
+            id: snippet.id.clone(),
+            path: snippet.path.display().to_string(),
+            kind: Some(snippet.kind.clone()),
+            language: language(&snippet.path),
+            line_start: snippet.lines.map(|lines| lines.start),
+            line_end: snippet.lines.map(|lines| lines.end),
+            params: params.clone(),
+        }
+    }
+
+    /// The template variables, the metadata wins over a parameter with the
+    /// same name.
+    fn variables(&self, content: &str) -> BTreeMap<String, Value> {
+        let mut variables = self
+            .params
+            .iter()
+            .map(|(name, value)| (name.clone(), Value::from(value.as_str())))
+            .collect::<BTreeMap<_, _>>();
+        let optional = |value: Option<Value>| value.unwrap_or(Value::from(()));
+
+        variables.extend([
+            ("snippet".to_string(), Value::from(content)),
+            (
+                "snippet_lines".to_string(),
+                Value::from(content.lines().map(str::to_string).collect::<Vec<_>>()),
+            ),
+            ("id".to_string(), Value::from(self.id.as_str())),
+            ("path".to_string(), Value::from(self.path.as_str())),
+            (
+                "kind".to_string(),
+                optional(
+                    self.kind
+                        .as_ref()
+                        .map(|kind| Value::from(format!("{kind}").to_lowercase())),
+                ),
+            ),
+            (
+                "language".to_string(),
+                optional(self.language.as_deref().map(Value::from)),
+            ),
+            (
+                "line_start".to_string(),
+                optional(self.line_start.map(Value::from)),
+            ),
+            (
+                "line_end".to_string(),
+                optional(self.line_end.map(Value::from)),
+            ),
+        ]);
+        variables
+    }
+}
+
+/// Renders the template with the snippet content and metadata.
+///
+/// # Errors
+///
+/// Returns an error when a `jinja` template is invalid or uses an undefined
+/// variable.
+pub fn render(
+    name: &str,
+    template: &str,
+    engine: Engine,
+    content: &str,
+    context: &TemplateContext,
+) -> Result<String, TemplateError> {
+    match engine {
+        // Renders as before the jinja engine, the `\n` escapes are replaced
+        // once the snippet is in, including the escapes of its content.
+        Engine::Legacy => Ok(RE_SNIPPET_TEMPLATE_PLACEHOLDER
+            .replace_all(template, |caps: &regex::Captures<'_>| {
+                let indent = caps.get(1).map_or("", |m| m.as_str());
+                content
+                    .lines()
+                    .map(|line| format!("{indent}{line}"))
+                    .collect::<Vec<_>>()
+                    .join(LINE_ENDING)
+            })
+            .replace("\\n", LINE_ENDING)),
+        Engine::Jinja => render_jinja(name, template, content, context),
+    }
+}
+
+fn render_jinja(
+    name: &str,
+    template: &str,
+    content: &str,
+    context: &TemplateContext,
+) -> Result<String, TemplateError> {
+    let template = legacy_placeholders(template);
+
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::SemiStrict);
+    env.set_keep_trailing_newline(true);
+    env.add_filter(PREFIX_LINES_FILTER, |value: String, prefix: String| {
+        value
+            .lines()
+            .map(|line| format!("{prefix}{line}"))
+            .collect::<Vec<_>>()
+            .join(LINE_ENDING)
+    });
+
+    let variables = context.variables(content);
+    let compiled = env
+        .template_from_str(&template)
+        .map_err(|err| TemplateError {
+            message: format!("invalid template `{name}`: {}", error_detail(&err)),
+            hint: None,
+        })?;
+
+    compiled.render(&variables).map_err(|err| {
+        if err.kind() == ErrorKind::UndefinedError {
+            let undefined = compiled
+                .undeclared_variables(false)
+                .into_iter()
+                .filter(|variable| !variables.contains_key(variable))
+                .map(|variable| format!("`{variable}`"))
+                .collect::<Vec<_>>();
+            let available = variables
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            TemplateError {
+                message: if undefined.is_empty() {
+                    format!("template `{name}` uses an undefined value")
+                } else {
+                    format!(
+                        "template `{name}` uses undefined variable {}",
+                        undefined.join(", ")
+                    )
+                },
+                hint: Some(format!("available variables: {available}")),
+            }
+        } else {
+            TemplateError {
+                message: format!("could not render template `{name}`: {}", error_detail(&err)),
+                hint: None,
+            }
+        }
+    })
+}
+
+/// Rewrites the legacy `{snippet}` placeholders into template expressions
+/// indenting the snippet lines, then replaces the `\n` escapes of the
+/// template text. `{{snippet}}` expressions are kept as is.
+fn legacy_placeholders(template: &str) -> String {
+    let mut rendered = String::new();
+    let mut last = 0;
+    for caps in RE_SNIPPET_TEMPLATE_PLACEHOLDER.captures_iter(template) {
+        let matched = caps.get(0).expect("the whole match always exists");
+        let is_expression = template[..matched.start()].ends_with('{')
+            || template[matched.end()..].starts_with('}');
+        if is_expression {
+            continue;
+        }
+
+        let indent = caps.get(1).map_or("", |m| m.as_str());
+        rendered.push_str(&template[last..matched.start()].replace("\\n", LINE_ENDING));
+        let _ = write!(
+            rendered,
+            "{{{{ snippet | {PREFIX_LINES_FILTER}({indent:?}) }}}}"
+        );
+        last = matched.end();
+    }
+    rendered.push_str(&template[last..].replace("\\n", LINE_ENDING));
+    rendered
+}
+
+fn error_detail(err: &minijinja::Error) -> String {
+    let detail = err
+        .detail()
+        .map_or_else(|| err.kind().to_string(), ToString::to_string);
+    err.line()
+        .map_or(detail.clone(), |line| format!("{detail} (line {line})"))
+}
+
+/// Returns the language of the file, as used by markdown code blocks.
+#[must_use]
+pub fn language(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let language = match extension.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "rb" => "ruby",
+        "yml" => "yaml",
+        "md" => "markdown",
+        "sh" => "sh",
+        "kt" => "kotlin",
+        "cs" => "csharp",
+        "hpp" | "cc" | "cxx" => "cpp",
+        "htm" => "html",
+        _ => return Some(extension),
+    };
+    Some(language.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn context() -> TemplateContext {
+        TemplateContext {
+            id: "usage".to_string(),
+            path: "src/main.rs".to_string(),
+            kind: Some(SnippetKind::Code),
+            language: language(&PathBuf::from("src/main.rs")),
+            line_start: Some(3),
+            line_end: Some(4),
+            params: BTreeMap::from([("title".to_string(), "Usage".to_string())]),
+        }
+    }
+
+    #[test]
+    fn can_render_legacy_placeholder() {
+        assert_eq!(
+            render(
+                "t",
+                r"```sh\n{snippet}\n```",
+                Engine::Legacy,
+                "a",
+                &context()
+            )
+            .unwrap(),
+            format!("```sh{LINE_ENDING}a{LINE_ENDING}```")
+        );
+        assert_eq!(
+            render(
+                "t",
+                "- item\n  {snippet}",
+                Engine::Legacy,
+                "a\nb",
+                &context()
+            )
+            .unwrap(),
+            format!("- item\n  a{LINE_ENDING}  b")
+        );
+        assert_eq!(
+            render(
+                "t",
+                r"```json\n{snippet}\n```",
+                Engine::Legacy,
+                r#"{"a":\n"b"}"#,
+                &context()
+            )
+            .unwrap(),
+            format!("```json{LINE_ENDING}{{\"a\":{LINE_ENDING}\"b\"}}{LINE_ENDING}```")
+        );
+        assert_eq!(
+            render(
+                "t",
+                "{{ x }} {% raw %} {snippet}",
+                Engine::Legacy,
+                "a",
+                &context()
+            )
+            .unwrap(),
+            "{{ x }} {% raw %} a"
+        );
+        assert_eq!(
+            render(
+                "t",
+                "- item\n  {snippet}\n{{snippet}}",
+                Engine::Jinja,
+                "a\nb",
+                &context()
+            )
+            .unwrap(),
+            format!("- item\n  a{LINE_ENDING}  b\na\nb")
+        );
+    }
+
+    #[test]
+    fn can_render_template() {
+        let template = r#"<details>
+<summary>{{ title }}{% if subtitle %} - {{ subtitle }}{% endif %}</summary>
+
+```{{ language }}
+{% for line in snippet_lines %}{{ loop.index }}: {{ line }}
+{% endfor %}```
+{{ id }} from {{ path }}:{{ line_start }}-{{ line_end }} ({{ kind }})
+</details>
+"#;
+
+        assert_eq!(
+            render(
+                "details",
+                template,
+                Engine::Jinja,
+                "let a = 1;\nrun(a);",
+                &context()
+            )
+            .unwrap(),
+            r"<details>
+<summary>Usage</summary>
+
+```rust
+1: let a = 1;
+2: run(a);
+```
+usage from src/main.rs:3-4 (code)
+</details>
+"
+        );
+    }
+
+    #[test]
+    fn fail_on_invalid_template() {
+        assert_eq!(
+            render("details", "{{ titel }}", Engine::Jinja, "a", &context()).unwrap_err(),
+            TemplateError {
+                message: "template `details` uses undefined variable `titel`".to_string(),
+                hint: Some(
+                    "available variables: id, kind, language, line_end, line_start, path, \
+                     snippet, snippet_lines, title"
+                        .to_string()
+                ),
+            }
+        );
+        assert_eq!(
+            render("details", "{% if title %}", Engine::Jinja, "a", &context())
+                .unwrap_err()
+                .message,
+            "invalid template `details`: unexpected end of input, expected end of block (line 1)"
+        );
+    }
+
+    #[test]
+    fn can_resolve_language() {
+        assert_eq!(language(Path::new("main.rs")), Some("rust".to_string()));
+        assert_eq!(language(Path::new("config.YML")), Some("yaml".to_string()));
+        assert_eq!(language(Path::new("query.sql")), Some("sql".to_string()));
+        assert_eq!(language(Path::new("Makefile")), None);
+    }
+}
@@ -0,0 +1,431 @@
+//! Parses and applies the `transform` attribute of a placeholder, a pipeline
+//! of stages applied to the snippet content in the declared order, e.g.
+//! `dedent | lines(3-12) | replace('foo', 'bar')`.
+use std::str::FromStr;
+
+use crate::LINE_ENDING;
+
+/// The registered transforms, with their usage.
+pub const TRANSFORMS: &[(&str, &str)] = &[
+    ("dedent", "dedent"),
+    ("trim", "trim"),
+    ("lines", "lines(start-end)"),
+    ("max_lines", "max_lines(count, 'marker')"),
+    ("replace", "replace('from', 'to')"),
+    ("wrap", "wrap(width)"),
+];
+
+/// A stage of the pipeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transform {
+    /// Removes the indentation shared by all the non blank lines.
+    Dedent,
+    /// Removes the blank lines at the start and at the end.
+    Trim,
+    /// Keeps the lines in the range, starting at 1 and inclusive. The end is
+    /// open when not set.
+    Lines { start: usize, end: Option<usize> },
+    /// Keeps the first `count` lines, adding the marker line when lines were
+    /// dropped.
+    MaxLines {
+        count: usize,
+        marker: Option<String>,
+    },
+    /// Replaces all the occurrences of `from` with `to`.
+    Replace { from: String, to: String },
+    /// Wraps the lines longer than `width` at spaces, keeping their
+    /// indentation.
+    Wrap { width: usize },
+}
+
+/// The transforms of a placeholder, empty when it has no `transform`
+/// attribute.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline(pub Vec<Transform>);
+
+/// A stage of the pipeline that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransformError {
+    pub message: String,
+    pub hint: String,
+}
+
+impl TransformError {
+    fn new(message: &str, hint: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            hint: hint.to_string(),
+        }
+    }
+
+    fn usage(name: &str, message: &str) -> Self {
+        let usage = TRANSFORMS
+            .iter()
+            .find(|(transform, _)| *transform == name)
+            .map_or(name, |(_, usage)| usage);
+        Self::new(message, &format!("expected `{usage}`"))
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = TransformError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        split_outside_quotes(input, '|')?
+            .iter()
+            .map(|stage| Transform::from_str(stage.trim()))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+impl FromStr for Transform {
+    type Err = TransformError;
+
+    fn from_str(stage: &str) -> Result<Self, Self::Err> {
+        let (name, args) = match stage.split_once('(') {
+            Some((name, rest)) => {
+                let Some(args) = rest.trim_end().strip_suffix(')') else {
+                    return Err(TransformError::usage(
+                        name.trim(),
+                        &format!("transform `{stage}` is missing `)`"),
+                    ));
+                };
+                let args = split_outside_quotes(args, ',')?
+                    .iter()
+                    .map(|arg| unquote(arg.trim()))
+                    .collect::<Vec<_>>();
+                (name.trim(), args)
+            }
+            None => (stage, vec![]),
+        };
+        let args = if args.len() == 1 && args[0].is_empty() {
+            vec![]
+        } else {
+            args
+        };
+
+        let transform = match (name, args.as_slice()) {
+            ("dedent", []) => Self::Dedent,
+            ("trim", []) => Self::Trim,
+            ("lines", [range]) => {
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                let start = parse_number(name, start)?;
+                let end = if end.trim().is_empty() {
+                    None
+                } else {
+                    Some(parse_number(name, end)?)
+                };
+                if start == 0 || end.is_some_and(|end| end < start) {
+                    return Err(TransformError::usage(
+                        name,
+                        &format!("invalid line range `{range}`"),
+                    ));
+                }
+                Self::Lines { start, end }
+            }
+            ("max_lines", [count]) => Self::MaxLines {
+                count: parse_number(name, count)?,
+                marker: None,
+            },
+            ("max_lines", [count, marker]) => Self::MaxLines {
+                count: parse_number(name, count)?,
+                marker: Some(marker.clone()),
+            },
+            ("replace", [from, to]) => {
+                if from.is_empty() {
+                    return Err(TransformError::usage(
+                        name,
+                        "`replace` needs a text to replace",
+                    ));
+                }
+                Self::Replace {
+                    from: from.clone(),
+                    to: to.clone(),
+                }
+            }
+            ("wrap", [width]) => {
+                let width = parse_number(name, width)?;
+                if width == 0 {
+                    return Err(TransformError::usage(name, "`wrap` width must be positive"));
+                }
+                Self::Wrap { width }
+            }
+            _ if TRANSFORMS.iter().any(|(transform, _)| *transform == name) => {
+                return Err(TransformError::usage(
+                    name,
+                    &format!("invalid arguments for transform `{name}`"),
+                ));
+            }
+            _ => {
+                let names = TRANSFORMS
+                    .iter()
+                    .map(|(transform, _)| *transform)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(TransformError::new(
+                    &format!("unknown transform `{name}`"),
+                    &format!("available transforms: {names}"),
+                ));
+            }
+        };
+
+        Ok(transform)
+    }
+}
+
+impl Pipeline {
+    /// Applies the stages to the content in the declared order.
+    #[must_use]
+    pub fn apply(&self, content: &str) -> String {
+        self.0
+            .iter()
+            .fold(content.to_string(), |content, transform| {
+                transform.apply(&content)
+            })
+    }
+}
+
+impl Transform {
+    #[must_use]
+    pub fn apply(&self, content: &str) -> String {
+        let lines = content.lines().collect::<Vec<_>>();
+        let lines = match self {
+            Self::Dedent => {
+                let indent = lines
+                    .iter()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| line.len() - line.trim_start().len())
+                    .min()
+                    .unwrap_or(0);
+                lines
+                    .iter()
+                    .map(|line| line.get(indent..).unwrap_or_default().to_string())
+                    .collect()
+            }
+            Self::Trim => {
+                let start = lines
+                    .iter()
+                    .position(|line| !line.trim().is_empty())
+                    .unwrap_or(lines.len());
+                let end = lines
+                    .iter()
+                    .rposition(|line| !line.trim().is_empty())
+                    .map_or(start, |end| end + 1);
+                lines[start..end].iter().map(ToString::to_string).collect()
+            }
+            Self::Lines { start, end } => lines
+                .iter()
+                .skip(start - 1)
+                .take(end.map_or(usize::MAX, |end| end + 1 - start))
+                .map(ToString::to_string)
+                .collect(),
+            Self::MaxLines { count, marker } => {
+                let mut kept = lines
+                    .iter()
+                    .take(*count)
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                if lines.len() > *count {
+                    kept.extend(marker.clone());
+                }
+                kept
+            }
+            Self::Replace { from, to } => return content.replace(from, to),
+            Self::Wrap { width } => lines.iter().flat_map(|line| wrap(line, *width)).collect(),
+        };
+
+        lines.join(LINE_ENDING)
+    }
+}
+
+/// Wraps the line at spaces, a word longer than the width is kept whole.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    if line.chars().count() <= width {
+        return vec![line.to_string()];
+    }
+
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let mut wrapped = vec![];
+    let mut current = indent.to_string();
+    for word in line.split_whitespace() {
+        let current_len = current.chars().count();
+        if current_len > indent.len() && current_len + 1 + word.chars().count() > width {
+            wrapped.push(std::mem::replace(&mut current, indent.to_string()));
+        }
+        if current.len() > indent.len() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    wrapped.push(current);
+    wrapped
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize, TransformError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| TransformError::usage(name, &format!("`{value}` is not a number")))
+}
+
+/// Splits the input on the separator, ignoring separators between quotes.
+fn split_outside_quotes(input: &str, separator: char) -> Result<Vec<String>, TransformError> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut quote = None;
+    for c in input.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == separator => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => (),
+        }
+        current.push(c);
+    }
+    if let Some(quote) = quote {
+        return Err(TransformError::new(
+            &format!("unclosed quote in `{input}`"),
+            &format!("close the value with `{quote}`"),
+        ));
+    }
+    parts.push(current);
+    Ok(parts)
+}
+
+fn unquote(value: &str) -> String {
+    ['\'', '"']
+        .iter()
+        .find_map(|quote| {
+            value
+                .strip_prefix(*quote)
+                .and_then(|value| value.strip_suffix(*quote))
+        })
+        .unwrap_or(value)
+        .to_string()
+}
+
+#[cfg(not(windows))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(pipeline: &str, content: &str) -> String {
+        Pipeline::from_str(pipeline).unwrap().apply(content)
+    }
+
+    #[test]
+    fn can_parse_pipeline() {
+        assert_eq!(
+            Pipeline::from_str("dedent | trim | lines(3-12) | max_lines(20, '...') | replace('a|b', \"c, d\") | wrap(80)"),
+            Ok(Pipeline(vec![
+                Transform::Dedent,
+                Transform::Trim,
+                Transform::Lines {
+                    start: 3,
+                    end: Some(12)
+                },
+                Transform::MaxLines {
+                    count: 20,
+                    marker: Some("...".to_string())
+                },
+                Transform::Replace {
+                    from: "a|b".to_string(),
+                    to: "c, d".to_string()
+                },
+                Transform::Wrap { width: 80 },
+            ]))
+        );
+        assert_eq!(
+            Pipeline::from_str("lines(3-)"),
+            Ok(Pipeline(vec![Transform::Lines {
+                start: 3,
+                end: None
+            }]))
+        );
+    }
+
+    #[test]
+    fn can_apply_transforms() {
+        let content = "\n    fn main() {\n        run();\n    }\n\n";
+        assert_eq!(
+            apply("trim | dedent", content),
+            "fn main() {\n    run();\n}"
+        );
+        assert_eq!(apply("trim | lines(2-3)", content), "        run();\n    }");
+        assert_eq!(apply("trim | lines(2)", content), "        run();");
+        assert_eq!(apply("trim | lines(2-)", content), "        run();\n    }");
+        assert_eq!(
+            apply("trim | dedent | max_lines(1, '// ...')", content),
+            "fn main() {\n// ..."
+        );
+        assert_eq!(
+            apply("trim | max_lines(5, '...')", content).lines().count(),
+            3
+        );
+        assert_eq!(
+            apply("trim | replace('run', 'exec') | dedent", content),
+            "fn main() {\n    exec();\n}"
+        );
+        assert_eq!(
+            apply("wrap(12)", "  one two three four\nshort"),
+            "  one two\n  three four\nshort"
+        );
+        assert_eq!(apply("wrap(3)", "abcdef gh"), "abcdef\ngh");
+    }
+
+    #[test]
+    fn fail_on_invalid_pipeline() {
+        let err = |pipeline: &str| Pipeline::from_str(pipeline).unwrap_err();
+
+        assert_eq!(
+            err("dedent | unknown"),
+            TransformError::new(
+                "unknown transform `unknown`",
+                "available transforms: dedent, trim, lines, max_lines, replace, wrap"
+            )
+        );
+        assert_eq!(
+            err("lines(5-2)"),
+            TransformError::new("invalid line range `5-2`", "expected `lines(start-end)`")
+        );
+        assert_eq!(
+            err("max_lines(many)"),
+            TransformError::new(
+                "`many` is not a number",
+                "expected `max_lines(count, 'marker')`"
+            )
+        );
+        assert_eq!(
+            err("replace('a')"),
+            TransformError::new(
+                "invalid arguments for transform `replace`",
+                "expected `replace('from', 'to')`"
+            )
+        );
+        assert_eq!(
+            err("wrap(80"),
+            TransformError::new(
+                "transform `wrap(80` is missing `)`",
+                "expected `wrap(width)`"
+            )
+        );
+        assert_eq!(
+            err("replace('a, 'b')"),
+            TransformError::new(
+                "unclosed quote in `replace('a, 'b')`",
+                "close the value with `'`"
+            )
+        );
+        assert_eq!(
+            err("dedent(2)"),
+            TransformError::new(
+                "invalid arguments for transform `dedent`",
+                "expected `dedent`"
+            )
+        );
+    }
+}
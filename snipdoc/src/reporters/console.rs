@@ -6,9 +6,13 @@ use std::{
 use console::style;
 
 use super::ReporterOutput;
-use crate::parser::{
-    injector::{InjectSnippets, InjectStats},
-    Snippet,
+use crate::{
+    db::Definition,
+    errors::Diagnostic,
+    parser::{
+        injector::{InjectSnippets, InjectStats, KindMismatch},
+        Snippet,
+    },
 };
 
 pub struct Output {}
@@ -23,8 +27,7 @@ impl ReporterOutput for Output {
                 .green()
                 .bold()
         );
-        let mut count = 1;
-        for (id, snippet) in snippets {
+        for (count, (id, snippet)) in (1..).zip(snippets.iter()) {
             println!(
                 "{:<5} {:<10} {:<40} {}",
                 count,
@@ -32,8 +35,16 @@ impl ReporterOutput for Output {
                 id,
                 snippet.path.display()
             );
+        }
+    }
 
-            count += 1;
+    fn collect_errors(&self, root_folder: &Path, errors: &BTreeMap<PathBuf, Diagnostic>) {
+        if !errors.is_empty() {
+            Self::print_errors(
+                root_folder,
+                "Could not collect snippets from the following files:",
+                errors,
+            );
         }
     }
 
@@ -71,13 +82,37 @@ impl ReporterOutput for Output {
                 style(format!("Error      : {}", stats.errors.len())).red()
             );
         }
+        if !stats.kind_mismatches.is_empty() {
+            println!(
+                "{}",
+                style(format!("Mismatch   : {}", stats.kind_mismatch_count)).yellow()
+            );
+        }
+        if !stats.deprecated.is_empty() {
+            println!(
+                "{}",
+                style(format!("Deprecated : {}", stats.deprecated_count)).yellow()
+            );
+        }
 
         if !stats.not_found.is_empty() {
             Self::print_not_found_snippets_to_inject(root_folder, &stats.not_found);
         }
 
+        if !stats.kind_mismatches.is_empty() {
+            Self::print_kind_mismatches(root_folder, &stats.kind_mismatches);
+        }
+
+        if !stats.deprecated.is_empty() {
+            Self::print_deprecated(root_folder, &stats.deprecated);
+        }
+
         if !stats.errors.is_empty() {
-            Self::print_errors(root_folder, &stats.errors);
+            Self::print_errors(
+                root_folder,
+                "Found errors in the following files:",
+                &stats.errors,
+            );
         }
 
         if !stats.inject_unique_files.is_empty() {
@@ -99,25 +134,87 @@ impl ReporterOutput for Output {
         }
 
         if !stats.errors.is_empty() {
-            Self::print_errors(root_folder, &stats.errors);
+            Self::print_errors(
+                root_folder,
+                "Found errors in the following files:",
+                &stats.errors,
+            );
         }
 
         if !stats.not_found.is_empty() {
             Self::print_not_found_snippets_to_inject(root_folder, &stats.not_found);
         }
+
+        if !stats.kind_mismatches.is_empty() {
+            Self::print_kind_mismatches(root_folder, &stats.kind_mismatches);
+        }
+
+        if !stats.deprecated.is_empty() {
+            Self::print_deprecated(root_folder, &stats.deprecated);
+        }
+    }
+
+    fn duplicates(&self, root_folder: &Path, duplicates: &BTreeMap<String, Vec<Definition>>) {
+        if duplicates.is_empty() {
+            return;
+        }
+
+        println!();
+        println!("{}", style("Snippets defined more than once:").bold());
+        for (id, definitions) in duplicates {
+            println!(" - snippet id: {id}");
+            for definition in definitions {
+                let path_view = dunce::canonicalize(root_folder).map_or(
+                    definition.path.as_path(),
+                    |absolute_path| {
+                        definition
+                            .path
+                            .strip_prefix(absolute_path)
+                            .unwrap_or(&definition.path)
+                    },
+                );
+                let location = definition
+                    .line
+                    .map_or_else(String::new, |line| format!(":{line}"));
+                let allow_override = if definition.allow_override {
+                    " (allow_override)"
+                } else {
+                    ""
+                };
+                println!(
+                    "   {:?} {}{location}{allow_override}",
+                    definition.kind,
+                    path_view.display()
+                );
+            }
+        }
     }
 }
 
 impl Output {
-    fn print_errors(root_folder: &Path, errors: &BTreeMap<PathBuf, String>) {
+    fn print_errors(root_folder: &Path, title: &str, errors: &BTreeMap<PathBuf, Diagnostic>) {
         println!();
-        println!("{}", style("Found errors in the following files:").bold());
-        for (file, error_msg) in errors {
+        println!("{}", style(title).bold());
+        for (file, diagnostic) in errors {
             let path_view = dunce::canonicalize(root_folder)
-                .map(|absolute_path| file.strip_prefix(absolute_path).unwrap_or(file))
-                .unwrap_or(file);
+                .map_or(file.as_path(), |absolute_path| {
+                    file.strip_prefix(absolute_path).unwrap_or(file)
+                });
+
+            let location = diagnostic.start.map_or_else(String::new, |start| {
+                format!(":{}:{}", start.line, start.column)
+            });
+            println!(
+                " - {}{location} : {}",
+                path_view.display(),
+                diagnostic.message
+            );
 
-            println!(" - {} : {error_msg}", path_view.display());
+            if let Some(excerpt) = diagnostic.render_excerpt() {
+                for line in excerpt.lines() {
+                    println!("   {line}");
+                }
+            }
         }
     }
 
@@ -126,34 +223,99 @@ impl Output {
         println!("{title}");
         for file in inject_files {
             let path_view = dunce::canonicalize(root_folder)
-                .map(|absolute_path| file.strip_prefix(absolute_path).unwrap_or(file))
-                .unwrap_or(file);
+                .map_or(file.as_path(), |absolute_path| {
+                    file.strip_prefix(absolute_path).unwrap_or(file)
+                });
             println!(" - {}", path_view.display());
         }
     }
 
     fn print_not_found_snippets_to_inject(
         root_folder: &Path,
-        not_found: &BTreeMap<PathBuf, HashSet<String>>,
+        not_found: &BTreeMap<PathBuf, BTreeMap<String, Vec<String>>>,
     ) {
         println!();
         println!("{}", style("Snippets to inject not found:").bold());
 
-        let mut entries: Vec<_> = not_found.iter().collect();
-        entries.sort_by(|(file1, _), (file2, _)| file1.cmp(file2));
-
-        for (file, snippet_ids) in entries {
+        for (file, snippet_ids) in not_found {
             let path_view = dunce::canonicalize(root_folder)
-                .map(|absolute_path| file.strip_prefix(absolute_path).unwrap_or(file))
-                .unwrap_or(file);
+                .map_or(file.as_path(), |absolute_path| {
+                    file.strip_prefix(absolute_path).unwrap_or(file)
+                });
 
-            let mut sorted_snippet_ids: Vec<_> = snippet_ids.iter().cloned().collect();
+            for (snippet_id, suggestions) in snippet_ids {
+                println!(
+                    " - {}, snippet id: {}{}",
+                    path_view.display(),
+                    snippet_id,
+                    did_you_mean(suggestions)
+                );
+            }
+        }
+    }
 
-            sorted_snippet_ids.sort();
+    fn print_kind_mismatches(
+        root_folder: &Path,
+        kind_mismatches: &BTreeMap<PathBuf, Vec<KindMismatch>>,
+    ) {
+        println!();
+        println!(
+            "{}",
+            style("Snippets to inject found in another source:").bold()
+        );
+
+        let absolute_root = dunce::canonicalize(root_folder);
+        let path_view = |path: &PathBuf| {
+            absolute_root
+                .as_ref()
+                .map_or(path.as_path(), |absolute_path| {
+                    path.strip_prefix(absolute_path).unwrap_or(path)
+                })
+                .display()
+                .to_string()
+        };
+
+        for (file, mismatches) in kind_mismatches {
+            for mismatch in mismatches {
+                println!(
+                    " - {}, snippet id: {}, inject_from: {}, found: {} in {}",
+                    path_view(file),
+                    mismatch.snippet_id,
+                    mismatch.expected,
+                    mismatch.found,
+                    path_view(&mismatch.source_path)
+                );
+            }
+        }
+    }
 
-            for snippet_id in sorted_snippet_ids {
-                println!(" - {}, snippet id: {}", path_view.display(), snippet_id);
+    fn print_deprecated(root_folder: &Path, deprecated: &BTreeMap<PathBuf, Vec<String>>) {
+        println!();
+        println!("{}", style("Deprecated snippet references:").bold());
+
+        for (file, descriptions) in deprecated {
+            let path_view = dunce::canonicalize(root_folder)
+                .map_or(file.as_path(), |absolute_path| {
+                    file.strip_prefix(absolute_path).unwrap_or(file)
+                });
+
+            for description in descriptions {
+                println!(" - {}, snippet id: {description}", path_view.display());
             }
         }
     }
 }
+
+/// Formats the suggested ids for a missing snippet id, empty without
+/// suggestions.
+fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+    let suggestions = suggestions
+        .iter()
+        .map(|id| format!("`{id}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(" (did you mean {suggestions}?)")
+}
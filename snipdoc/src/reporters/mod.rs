@@ -1,14 +1,23 @@
 pub mod console;
 pub mod table;
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
-use crate::parser::{
-    injector::{InjectSnippets, InjectStats},
-    Snippet,
+use crate::{
+    db::Definition,
+    errors::Diagnostic,
+    parser::{
+        injector::{InjectSnippets, InjectStats},
+        Snippet,
+    },
 };
 
 pub trait ReporterOutput: Sync {
     fn snippets(&self, root_folder: &Path, snippets: &BTreeMap<String, Snippet>);
+    fn collect_errors(&self, root_folder: &Path, errors: &BTreeMap<PathBuf, Diagnostic>);
     fn inject(&self, root_folder: &Path, result: &InjectSnippets);
     fn check(&self, _root_folder: &Path, _result: &InjectStats) {}
+    fn duplicates(&self, _root_folder: &Path, _duplicates: &BTreeMap<String, Vec<Definition>>) {}
 }
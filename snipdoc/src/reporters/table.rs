@@ -1,16 +1,100 @@
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use tabled::{builder::Builder, settings::Style};
 
 use super::ReporterOutput;
-use crate::parser::{
-    injector::{InjectSnippets, InjectStatus, InjectedContent},
-    Snippet,
+use crate::{
+    errors::Diagnostic,
+    parser::{
+        injector::{InjectSnippets, InjectStatus, InjectedContent},
+        Snippet,
+    },
 };
 
 pub struct Output {}
 
-impl Output {}
+impl Output {
+    fn diagnostic_view(diagnostic: &Diagnostic) -> String {
+        diagnostic.render_excerpt().map_or_else(
+            || diagnostic.to_string(),
+            |excerpt| format!("{diagnostic}\n{excerpt}"),
+        )
+    }
+
+    /// Returns the action, snippet id and details columns of an inject
+    /// status.
+    fn inject_status_view(root_folder: &Path, status: &InjectStatus) -> [String; 3] {
+        match status {
+            InjectStatus::Equal { snippet_id, source } => [
+                "equal".to_string(),
+                snippet_id.clone(),
+                format!("Source: {source:?}"),
+            ],
+            InjectStatus::Injected {
+                snippet_id,
+                content: _,
+                source,
+            } => [
+                "injected".to_string(),
+                snippet_id.clone(),
+                format!("Source: {source:?}"),
+            ],
+            InjectStatus::NotFound {
+                snippet_id,
+                snippet_kind,
+                suggestions,
+            } => {
+                let did_you_mean = if suggestions.is_empty() {
+                    String::new()
+                } else {
+                    format!("\nDid you mean: {}", suggestions.join(", "))
+                };
+                [
+                    "not-found-snippets".to_string(),
+                    snippet_id.clone(),
+                    format!("Inject from: {snippet_kind:?}{did_you_mean}"),
+                ]
+            }
+            InjectStatus::Deprecated {
+                snippet_id,
+                resolved_id,
+                message,
+            } => [
+                "deprecated".to_string(),
+                snippet_id.clone(),
+                message
+                    .as_ref()
+                    .map_or_else(|| format!("Renamed to: {resolved_id}"), Clone::clone),
+            ],
+            InjectStatus::KindMismatch {
+                snippet_id,
+                expected,
+                found,
+                source_path,
+            } => {
+                let source_view = dunce::canonicalize(root_folder).map_or(
+                    source_path.as_path(),
+                    |absolute_path| {
+                        source_path
+                            .strip_prefix(absolute_path)
+                            .unwrap_or(source_path)
+                    },
+                );
+                [
+                    "kind-mismatch".to_string(),
+                    snippet_id.clone(),
+                    format!(
+                        "Inject from: {expected:?}\nFound: {found:?} in {}",
+                        source_view.display()
+                    ),
+                ]
+            }
+        }
+    }
+}
 
 impl ReporterOutput for Output {
     fn snippets(&self, root_folder: &Path, snippets: &BTreeMap<String, Snippet>) {
@@ -18,16 +102,14 @@ impl ReporterOutput for Output {
         builder.push_record(["#", "Snippet Kind", "ID", "Path"]);
 
         println!("Found {} snippets", snippets.len());
-        let mut count = 1;
-        for (id, snippet) in snippets {
-            let path_view = dunce::canonicalize(root_folder)
-                .map(|absolute_path| {
+        for (count, (id, snippet)) in (1..).zip(snippets.iter()) {
+            let path_view =
+                dunce::canonicalize(root_folder).map_or(snippet.path.as_path(), |absolute_path| {
                     snippet
                         .path
                         .strip_prefix(absolute_path)
                         .unwrap_or(&snippet.path)
-                })
-                .unwrap_or(&snippet.path);
+                });
 
             builder.push_record([
                 format!("{count}"),
@@ -35,7 +117,30 @@ impl ReporterOutput for Output {
                 id.to_string(),
                 format!("{}", path_view.display()),
             ]);
-            count += 1;
+        }
+
+        println!("{}", builder.build().with(Style::modern()));
+    }
+
+    fn collect_errors(&self, root_folder: &Path, errors: &BTreeMap<PathBuf, Diagnostic>) {
+        if errors.is_empty() {
+            return;
+        }
+
+        let mut builder = Builder::default();
+        builder.push_record(["Path", "Error"]);
+
+        println!("Could not collect snippets from {} files", errors.len());
+        for (file, diagnostic) in errors {
+            let path_view = dunce::canonicalize(root_folder)
+                .map_or(file.as_path(), |absolute_path| {
+                    file.strip_prefix(absolute_path).unwrap_or(file)
+                });
+
+            builder.push_record([
+                format!("{}", path_view.display()),
+                Self::diagnostic_view(diagnostic),
+            ]);
         }
 
         println!("{}", builder.build().with(Style::modern()));
@@ -47,53 +152,30 @@ impl ReporterOutput for Output {
 
         for (file, status) in result.iter() {
             let path_view = dunce::canonicalize(root_folder)
-                .map(|absolute_path| file.strip_prefix(absolute_path).unwrap_or(file))
-                .unwrap_or(file);
+                .map_or(file.as_path(), |absolute_path| {
+                    file.strip_prefix(absolute_path).unwrap_or(file)
+                });
 
             match status {
                 InjectedContent::Injected(summary) => {
                     for action in &summary.actions {
-                        match action {
-                            InjectStatus::Equal { snippet_id } => {
-                                builder.push_record([
-                                    format!("{}", path_view.display()),
-                                    "equal".to_string(),
-                                    snippet_id.to_string(),
-                                    String::new(),
-                                ]);
-                            }
-                            InjectStatus::Injected {
-                                snippet_id,
-                                content: _,
-                            } => {
-                                builder.push_record([
-                                    format!("{}", path_view.display()),
-                                    "injected".to_string(),
-                                    snippet_id.to_string(),
-                                    String::new(),
-                                ]);
-                            }
-                            InjectStatus::NotFound {
-                                snippet_id,
-                                snippet_kind,
-                            } => {
-                                builder.push_record([
-                                    format!("{}", path_view.display()),
-                                    "not-found-snippets".to_string(),
-                                    snippet_id.to_string(),
-                                    format!("Inject from: {snippet_kind:?}"),
-                                ]);
-                            }
-                        }
+                        let [action, snippet_id, details] =
+                            Self::inject_status_view(root_folder, action);
+                        builder.push_record([
+                            format!("{}", path_view.display()),
+                            action,
+                            snippet_id,
+                            details,
+                        ]);
                     }
                 }
                 InjectedContent::None => (),
-                InjectedContent::Error(error) => {
+                InjectedContent::Error(diagnostic) => {
                     builder.push_record([
                         format!("{}", path_view.display()),
                         "error".to_string(),
                         String::new(),
-                        error.to_string(),
+                        Self::diagnostic_view(diagnostic),
                     ]);
                 }
             }
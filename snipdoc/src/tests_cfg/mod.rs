@@ -1,6 +1,8 @@
 use std::{collections::BTreeMap, path::PathBuf};
 pub mod redact;
-use crate::parser::{collector::CollectSnippet, Snippet, SnippetKind, SnippetTemplate};
+use crate::parser::{
+    collector::CollectSnippet, template::Engine, Snippet, SnippetKind, SnippetTemplate,
+};
 
 #[must_use]
 pub fn get_collect_snippets() -> Vec<CollectSnippet> {
@@ -11,6 +13,13 @@ pub fn get_collect_snippets() -> Vec<CollectSnippet> {
             inject_from: None,
             tag_open: "<snip id=\"description\">".to_string(),
             tag_close: "<!-- </snip> -->\n".to_string(),
+            nested: vec![],
+            hidden: vec![],
+            lines: None,
+            line: 1,
+            allow_override: false,
+            aliases: vec![],
+            deprecated: None,
         },
         CollectSnippet {
             id: "installation".to_string(),
@@ -22,6 +31,13 @@ pub fn get_collect_snippets() -> Vec<CollectSnippet> {
             inject_from: None,
             tag_open: "<snip id=\"install\">".to_string(),
             tag_close: "<!-- </snip> -->\n".to_string(),
+            nested: vec![],
+            hidden: vec![],
+            lines: None,
+            line: 1,
+            allow_override: false,
+            aliases: vec![],
+            deprecated: None,
         },
         CollectSnippet {
             id: "from-yaml".to_string(),
@@ -29,6 +45,13 @@ pub fn get_collect_snippets() -> Vec<CollectSnippet> {
             inject_from: Some(SnippetKind::Yaml),
             tag_open: "<snip id=\"from-yaml\">".to_string(),
             tag_close: "<!-- </snip> -->\n".to_string(),
+            nested: vec![],
+            hidden: vec![],
+            lines: None,
+            line: 1,
+            allow_override: false,
+            aliases: vec![],
+            deprecated: None,
         },
     ]
 }
@@ -40,12 +63,14 @@ pub fn get_custom_templates() -> BTreeMap<String, SnippetTemplate> {
             "CUSTOM_ID_1".to_string(),
             SnippetTemplate {
                 content: "---> {snippet}".to_string(),
+                engine: Engine::Legacy,
             },
         ),
         (
             "CUSTOM_ID_2".to_string(),
             SnippetTemplate {
                 content: "({snippet})".to_string(),
+                engine: Engine::Legacy,
             },
         ),
     ])
@@ -58,6 +83,11 @@ pub fn get_snippet() -> Snippet {
         content: "$ cargo install snipdoc\n$ snipdoc --version".to_string(),
         kind: SnippetKind::Code,
         path: PathBuf::from("main.rs"),
+        nested: vec![],
+        hidden: vec![],
+        lines: None,
+        aliases: vec![],
+        deprecated: None,
     }
 }
 
@@ -71,6 +101,11 @@ pub fn get_snippet_to_inject() -> BTreeMap<String, Snippet> {
                 content: "snipdoc".to_string(),
                 kind: SnippetKind::Code,
                 path: PathBuf::from("main.rs"),
+                nested: vec![],
+                hidden: vec![],
+                lines: None,
+                aliases: vec![],
+                deprecated: None,
             },
         ),
         (
@@ -80,6 +115,11 @@ pub fn get_snippet_to_inject() -> BTreeMap<String, Snippet> {
                 content: "$ cargo install snipdoc\n$ snipdoc --version".to_string(),
                 kind: SnippetKind::Code,
                 path: PathBuf::from("main.rs"),
+                nested: vec![],
+                hidden: vec![],
+                lines: None,
+                aliases: vec![],
+                deprecated: None,
             },
         ),
         (
@@ -89,6 +129,11 @@ pub fn get_snippet_to_inject() -> BTreeMap<String, Snippet> {
                 content: "inject_from_yaml".to_string(),
                 kind: SnippetKind::Yaml,
                 path: PathBuf::from("main.rs"),
+                nested: vec![],
+                hidden: vec![],
+                lines: None,
+                aliases: vec![],
+                deprecated: None,
             },
         ),
     ])
@@ -0,0 +1,5 @@
+// <snip id="usage">
+// snip:hide-start
+let client = Client::new();
+client.run();
+// </snip>
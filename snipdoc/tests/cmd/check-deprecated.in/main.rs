@@ -0,0 +1,7 @@
+// <snip id="install" aliases="installation, setup">
+// cargo install snipdoc
+// </snip>
+
+// <snip id="usage-v1" deprecated="use usage instead">
+// snipdoc run
+// </snip>
@@ -0,0 +1,3 @@
+// <snip id="install">
+// cargo install snipdoc
+// </snip>
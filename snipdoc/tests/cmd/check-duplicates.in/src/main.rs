@@ -0,0 +1,15 @@
+// <snip id="install">
+// ```sh
+// cargo install snipdoc
+// ```
+// </snip>
+
+// <snip id="usage">
+fn main() {}
+// </snip>
+
+// <snip id="usage" allow_override>
+fn main() {
+    println!("snipdoc");
+}
+// </snip>
@@ -0,0 +1,3 @@
+// <snip id="installation">
+// cargo install snipdoc
+// </snip>
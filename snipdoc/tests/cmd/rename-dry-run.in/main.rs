@@ -0,0 +1,7 @@
+// <snip id="installation">
+// cargo install snipdoc
+// </snip id="installation">
+
+// <snip id="usage">
+// snipdoc run
+// </snip>
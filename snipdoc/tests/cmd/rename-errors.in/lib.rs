@@ -0,0 +1,2 @@
+// <snip id="install">
+// cargo add snipdoc
@@ -0,0 +1,11 @@
+// <snip id="installation">
+// cargo add crate_b
+// </snip>
+
+// <snip id="example" namespace="shared">
+// shared example
+// </snip>
+
+// <snip id="setup">
+// crate_b setup
+// </snip>
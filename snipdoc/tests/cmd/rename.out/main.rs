@@ -0,0 +1,7 @@
+// <snip id="install">
+// cargo install snipdoc
+// </snip id="install">
+
+// <snip id="usage">
+// snipdoc run
+// </snip>
@@ -0,0 +1,10 @@
+fn main() {
+    // <snip id="usage">
+    let config = Config::default(); // snip:hide
+    // snip:hide-start
+    assert!(config.valid());
+    // snip:hide-end
+    let data = load(&config).unwrap(); // snip:...
+    run(data);
+    // </snip>
+}
@@ -0,0 +1,3 @@
+// <snip id="installation">
+// cargo add crate_a
+// </snip>
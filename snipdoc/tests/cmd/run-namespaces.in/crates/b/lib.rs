@@ -0,0 +1,7 @@
+// <snip id="installation">
+// cargo add crate_b
+// </snip>
+
+// <snip id="example" namespace="shared">
+// shared example
+// </snip>
@@ -0,0 +1,3 @@
+-- <snip id="sql-query">
+SELECT id, name FROM snippets;
+-- </snip>
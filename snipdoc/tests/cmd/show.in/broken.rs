@@ -0,0 +1,2 @@
+// <snip id="broken">
+fn broken() {}